@@ -0,0 +1,364 @@
+use crate::password::v3;
+use crate::rutil::safe_vec::SafeVec;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write};
+use std::ops::Deref;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long the agent keeps keys that are not used, by default
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// How many keys the agent keeps at most, the oldest ones are forgotten first
+const MAX_KEYS: usize = 8;
+
+/// Requests are never big, this is just to not read garbage forever
+const MAX_MESSAGE_LEN: u32 = 64 * 1024 * 1024;
+
+/// How long a client or the agent waits for the other side before giving up
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Requests, followed by the length of the payload (u32, big endian) and the payload.
+/// Asks to decrypt the password file in the payload, the answer is the passwords as JSON
+const REQUEST_DECRYPT: u8 = b'd';
+/// Asks to encrypt passwords as JSON for a password file, the payload is the length of the
+/// password file (u32, big endian), the password file and the JSON. The answer is the new file.
+const REQUEST_ENCRYPT: u8 = b'e';
+/// Gives a key to the agent
+const REQUEST_ADD: u8 = b'a';
+/// Asks the agent to forget all its keys
+const REQUEST_LOCK: u8 = b'l';
+
+// Responses, followed by the length of the payload (u32, big endian) and the payload.
+const RESPONSE_OK: u8 = 0;
+const RESPONSE_NOT_FOUND: u8 = 1;
+const RESPONSE_ERROR: u8 = 2;
+
+/// Path of the agent's socket, ie: `/run/user/1000/rooster-agent.sock`
+///
+/// `$XDG_RUNTIME_DIR` is only readable by its owner, and the socket itself is only accessible
+/// to its owner too.
+pub fn socket_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")?;
+    if dir.is_empty() {
+        return None;
+    }
+    Some(PathBuf::from(dir).join("rooster-agent.sock"))
+}
+
+fn write_message(stream: &mut UnixStream, kind: u8, payload: &[u8]) -> IoResult<()> {
+    let mut message = SafeVec::new(Vec::with_capacity(payload.len() + 5));
+    message.inner_mut().write_u8(kind)?;
+    message
+        .inner_mut()
+        .write_u32::<BigEndian>(payload.len() as u32)?;
+    message.inner_mut().extend_from_slice(payload);
+    stream.write_all(message.deref())?;
+    stream.flush()
+}
+
+fn read_message(stream: &mut UnixStream) -> IoResult<(u8, SafeVec)> {
+    let kind = stream.read_u8()?;
+    let len = stream.read_u32::<BigEndian>()?;
+    if len > MAX_MESSAGE_LEN {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            "the message is too big",
+        ));
+    }
+    let mut payload = SafeVec::new(vec![0u8; len as usize]);
+    stream.read_exact(payload.inner_mut())?;
+    Ok((kind, payload))
+}
+
+/// Sends a request to the agent, if one is running
+fn request(kind: u8, payload: &[u8]) -> IoResult<(u8, SafeVec)> {
+    let path = socket_path()
+        .ok_or_else(|| IoError::new(ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    write_message(&mut stream, kind, payload)?;
+    read_message(&mut stream)
+}
+
+/// Asks the agent to decrypt a password file, without asking for the master password
+pub fn decrypt(input: &[u8]) -> Option<SafeVec> {
+    match request(REQUEST_DECRYPT, input) {
+        Ok((RESPONSE_OK, json_schema)) => Some(json_schema),
+        _ => None,
+    }
+}
+
+/// Asks the agent to encrypt passwords for the password file it decrypted before
+pub fn encrypt(input: &[u8], json_schema: &[u8]) -> IoResult<Vec<u8>> {
+    let mut payload = SafeVec::new(Vec::with_capacity(input.len() + json_schema.len() + 4));
+    payload
+        .inner_mut()
+        .write_u32::<BigEndian>(input.len() as u32)?;
+    payload.inner_mut().extend_from_slice(input);
+    payload.inner_mut().extend_from_slice(json_schema);
+
+    match request(REQUEST_ENCRYPT, payload.deref())? {
+        (RESPONSE_OK, contents) => Ok(contents.deref().to_vec()),
+        (RESPONSE_NOT_FOUND, _) => Err(IoError::other(
+            "the agent forgot your master password meanwhile",
+        )),
+        _ => Err(IoError::other("the agent could not encrypt your passwords")),
+    }
+}
+
+/// The user the other end of the socket runs as
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> IoResult<libc::uid_t> {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result != 0 {
+        return Err(IoError::last_os_error());
+    }
+    Ok(credentials.uid)
+}
+
+/// The user the other end of the socket runs as
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> IoResult<libc::uid_t> {
+    let mut uid: libc::uid_t = 0;
+    let mut gid: libc::gid_t = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(IoError::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Hands the key over to the agent, so that the next commands don't need the master password
+///
+/// Nothing happens if no agent is running.
+pub fn add_key(key: &[u8]) {
+    let _ = request(REQUEST_ADD, key);
+}
+
+/// Makes the agent forget all its keys, returns whether an agent was running
+pub fn lock() -> IoResult<bool> {
+    match request(REQUEST_LOCK, &[]) {
+        Ok((RESPONSE_OK, _)) => Ok(true),
+        Ok(_) => Err(IoError::other("the agent refused to lock")),
+        Err(err)
+            if err.kind() == ErrorKind::NotFound || err.kind() == ErrorKind::ConnectionRefused =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// A key kept in memory that can't be swapped to disk
+struct LockedKey {
+    key: SafeVec,
+}
+
+impl LockedKey {
+    fn new(key: &[u8]) -> IoResult<LockedKey> {
+        // The buffer must never move once locked, so it gets its final size right away.
+        let key = SafeVec::new(key.to_vec());
+        if unsafe { libc::mlock(key.as_ptr() as *const libc::c_void, key.len()) } != 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok(LockedKey { key })
+    }
+}
+
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        // The key itself is zeroed when the SafeVec is dropped, right after this.
+        unsafe { libc::munlock(self.key.as_ptr() as *const libc::c_void, self.key.len()) };
+    }
+}
+
+/// The agent, which keeps the keys derived from master passwords
+///
+/// The keys never leave it, it decrypts and encrypts password files for other Rooster commands.
+pub struct Agent {
+    listener: UnixListener,
+    path: PathBuf,
+    keys: Vec<LockedKey>,
+    timeout: Duration,
+    last_used: Instant,
+}
+
+impl Agent {
+    /// Listens on the agent's socket, unless another agent is already running
+    pub fn bind(timeout: Duration) -> IoResult<Agent> {
+        let path = socket_path()
+            .ok_or_else(|| IoError::new(ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(IoError::new(
+                    ErrorKind::AddrInUse,
+                    "another agent is already running",
+                ));
+            }
+            // Left behind by an agent that was killed.
+            std::fs::remove_file(&path)?;
+        }
+
+        // The socket must never be accessible to others, not even for a moment.
+        let umask = unsafe { libc::umask(0o177) };
+        let listener = UnixListener::bind(&path);
+        unsafe { libc::umask(umask) };
+        let listener = listener?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        // Keys should not end up in a core dump either.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::prctl(libc::PR_SET_DUMPABLE, 0);
+        }
+
+        Ok(Agent {
+            listener,
+            path,
+            keys: Vec::new(),
+            timeout,
+            last_used: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Answers requests forever, forgetting the keys once they haven't been used for a while
+    pub fn run(&mut self) -> IoResult<()> {
+        loop {
+            let wait = if self.keys.is_empty() {
+                // Nothing to forget, so no need to wake up.
+                -1
+            } else {
+                let idle = self.last_used.elapsed();
+                if idle >= self.timeout {
+                    self.keys.clear();
+                    continue;
+                }
+                (self.timeout - idle).as_millis().min(i32::MAX as u128) as i32
+            };
+
+            let mut fd = libc::pollfd {
+                fd: self.listener.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if unsafe { libc::poll(&mut fd, 1, wait) } < 0 {
+                let err = IoError::last_os_error();
+                if err.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            if fd.revents & libc::POLLIN == 0 {
+                continue;
+            }
+
+            // A client that goes away or misbehaves must not take the agent down.
+            if let Ok((mut stream, _)) = self.listener.accept() {
+                let _ = self.answer(&mut stream);
+            }
+        }
+    }
+
+    /// The key that opens a password file
+    fn find_key(&self, input: &[u8]) -> Option<&LockedKey> {
+        self.keys
+            .iter()
+            .find(|key| v3::key_matches(key.key.deref(), input))
+    }
+
+    fn answer(&mut self, stream: &mut UnixStream) -> IoResult<()> {
+        // The socket is only accessible to its owner, but other users must never get an answer.
+        if peer_uid(stream)? != unsafe { libc::getuid() } {
+            return Err(IoError::new(
+                ErrorKind::PermissionDenied,
+                "the client runs as another user",
+            ));
+        }
+
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let (kind, payload) = read_message(stream)?;
+
+        match kind {
+            REQUEST_DECRYPT => {
+                let decrypted = self
+                    .find_key(&payload)
+                    .map(|key| v3::decrypt_with_key(key.key.deref(), &payload));
+                match decrypted {
+                    Some(Ok(json_schema)) => {
+                        write_message(stream, RESPONSE_OK, json_schema.deref())?;
+                        self.last_used = Instant::now();
+                    }
+                    Some(Err(_)) => write_message(stream, RESPONSE_ERROR, &[])?,
+                    None => write_message(stream, RESPONSE_NOT_FOUND, &[])?,
+                }
+            }
+            REQUEST_ENCRYPT => {
+                let mut reader = payload.deref();
+                let input_len = reader.read_u32::<BigEndian>()? as usize;
+                if input_len > reader.len() {
+                    write_message(stream, RESPONSE_ERROR, &[])?;
+                    return Ok(());
+                }
+                let (input, json_schema) = reader.split_at(input_len);
+
+                let encrypted = self
+                    .find_key(input)
+                    .map(|key| v3::encrypt_with_key(key.key.deref(), input, json_schema));
+                match encrypted {
+                    Some(Ok(contents)) => {
+                        write_message(stream, RESPONSE_OK, &contents)?;
+                        self.last_used = Instant::now();
+                    }
+                    Some(Err(_)) => write_message(stream, RESPONSE_ERROR, &[])?,
+                    None => write_message(stream, RESPONSE_NOT_FOUND, &[])?,
+                }
+            }
+            REQUEST_ADD => {
+                if !self.keys.iter().any(|key| *key.key == *payload) {
+                    if self.keys.len() == MAX_KEYS {
+                        self.keys.remove(0);
+                    }
+                    self.keys.push(LockedKey::new(&payload)?);
+                }
+                self.last_used = Instant::now();
+                write_message(stream, RESPONSE_OK, &[])?;
+            }
+            REQUEST_LOCK => {
+                self.keys.clear();
+                write_message(stream, RESPONSE_OK, &[])?;
+            }
+            _ => write_message(stream, RESPONSE_ERROR, &[])?,
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Agent {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
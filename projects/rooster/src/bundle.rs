@@ -0,0 +1,124 @@
+//! Encrypted bundles, to hand some passwords over to someone else
+//!
+//! A bundle is a password file of its own, encrypted with a password agreed upon with the
+//! recipient instead of the master password. It is encoded as text, so that it can be pasted
+//! in an email or a chat:
+//!
+//! ```text
+//! -----BEGIN ROOSTER BUNDLE-----
+//! Passwords: 5
+//!
+//! <the password file in base64, 64 characters per line>
+//! -----END ROOSTER BUNDLE-----
+//! ```
+
+use crate::password::v3::{Password, PasswordStore};
+use crate::password::PasswordError;
+use crate::rutil::safe_string::SafeString;
+use crate::rutil::safe_vec::SafeVec;
+use openssl::base64;
+
+const BEGIN: &str = "-----BEGIN ROOSTER BUNDLE-----";
+const END: &str = "-----END ROOSTER BUNDLE-----";
+const LINE_LEN: usize = 64;
+
+/// Encrypts passwords into a bundle that only opens with the recipient password
+pub fn seal(
+    passwords: Vec<Password>,
+    recipient_password: SafeString,
+) -> Result<String, PasswordError> {
+    let count = passwords.len();
+    let mut store = PasswordStore::new(recipient_password)?;
+    for password in passwords {
+        store.add_password(password)?;
+    }
+
+    let encoded = base64::encode_block(&store.to_bytes()?);
+    let mut bundle = format!("{}\nPasswords: {}\n\n", BEGIN, count);
+    for line in encoded.as_bytes().chunks(LINE_LEN) {
+        bundle.push_str(&String::from_utf8_lossy(line));
+        bundle.push('\n');
+    }
+    bundle.push_str(END);
+    bundle.push('\n');
+    Ok(bundle)
+}
+
+/// Decrypts the passwords of a bundle
+///
+/// Text around the bundle is left out, ie: the rest of an email.
+pub fn open(text: &str, recipient_password: SafeString) -> Result<Vec<Password>, PasswordError> {
+    let start = match text.find(BEGIN) {
+        Some(start) => start + BEGIN.len(),
+        None => return Err(PasswordError::CorruptionError),
+    };
+    let end = match text[start..].find(END) {
+        Some(end) => start + end,
+        None => return Err(PasswordError::CorruptionError),
+    };
+
+    // Headers have a colon, which is not a base64 character
+    let encoded: String = text[start..end]
+        .lines()
+        .filter(|line| !line.contains(':'))
+        .flat_map(|line| line.chars().filter(|c| !c.is_ascii_whitespace()))
+        .collect();
+    let input = base64::decode_block(&encoded).map_err(|_| PasswordError::CorruptionError)?;
+
+    let store = PasswordStore::from_input(recipient_password, SafeVec::new(input))?;
+    Ok(store.get_all_passwords().into_iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{open, seal};
+    use crate::password::v3::Password;
+    use crate::password::PasswordError;
+    use crate::rutil::safe_string::SafeString;
+
+    #[test]
+    fn test_seal_and_open() {
+        let bundle = seal(
+            vec![
+                Password::new("Youtube", "yt@example.com", "abcd"),
+                Password::new("Google", "me@example.com", "efgh"),
+            ],
+            SafeString::from_string(String::from("shared")),
+        )
+        .unwrap();
+        assert!(bundle.starts_with("-----BEGIN ROOSTER BUNDLE-----\nPasswords: 2\n\n"));
+        assert!(bundle.ends_with("-----END ROOSTER BUNDLE-----\n"));
+        assert!(!bundle.contains("abcd"));
+
+        let email = format!("Hi, here they are:\n\n{}\nCheers", bundle);
+        let passwords = open(&email, SafeString::from_string(String::from("shared"))).unwrap();
+        assert_eq!(passwords.len(), 2);
+        assert_eq!(passwords[0].name, "Google");
+        assert_eq!(
+            passwords[1].password,
+            SafeString::from_string(String::from("abcd"))
+        );
+
+        assert!(matches!(
+            open(&bundle, SafeString::from_string(String::from("wrong"))),
+            Err(PasswordError::DecryptionError)
+        ));
+        assert!(matches!(
+            open(
+                "nothing here",
+                SafeString::from_string(String::from("shared"))
+            ),
+            Err(PasswordError::CorruptionError)
+        ));
+
+        // Pasting can mangle line endings, but not the base64 characters
+        let pasted = bundle.replace('\n', "\r\n");
+        let passwords = open(&pasted, SafeString::from_string(String::from("shared"))).unwrap();
+        assert_eq!(passwords.len(), 2);
+        let mangled = bundle.replacen("\n\n", "\n\n!", 1);
+        assert!(matches!(
+            open(&mangled, SafeString::from_string(String::from("shared"))),
+            Err(PasswordError::CorruptionError)
+        ));
+    }
+}
@@ -1,106 +1,409 @@
 use crate::password;
 use crate::rclio::{CliInputOutput, OutputType};
+use crate::rutil::print_tty::print_tty;
 use crate::rutil::safe_string::SafeString;
 
+#[cfg(all(unix, not(target_os = "macos")))]
+use crate::master_password::split_words;
 use std::ops::Deref;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::os::unix::ffi::OsStrExt;
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::{ffi::OsString, path::PathBuf};
+
+/// Number of seconds copied passwords stay in the clipboard, unless told otherwise
+pub const DEFAULT_CLEAR_AFTER: u64 = 30;
+
+/// Where to copy: the clipboard, or the primary selection that is pasted with a middle click
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+    Both,
+}
+
+impl Selection {
+    /// The selections to copy to, one at a time
+    fn parts(self) -> &'static [Selection] {
+        match self {
+            Selection::Clipboard => &[Selection::Clipboard],
+            Selection::Primary => &[Selection::Primary],
+            Selection::Both => &[Selection::Clipboard, Selection::Primary],
+        }
+    }
+}
+
+/// How to use the clipboard, from the `--selection`, `--clear-after`, `--no-clear` and
+/// `--osc52` options
+pub struct ClipboardOptions {
+    pub selection: Selection,
+    /// Number of seconds after which the clipboard is cleared, `None` to leave it as is
+    pub clear_after: Option<u64>,
+    /// Copy through the terminal with an OSC 52 escape sequence instead of the system clipboard
+    pub osc52: bool,
+}
+
+impl ClipboardOptions {
+    pub fn from_matches(matches: &clap::ArgMatches) -> ClipboardOptions {
+        let clear_after = if matches.is_present("no-clear") {
+            None
+        } else {
+            Some(
+                matches
+                    .value_of("clear-after")
+                    .and_then(|seconds| seconds.parse::<u64>().ok())
+                    .unwrap_or(DEFAULT_CLEAR_AFTER),
+            )
+        };
+
+        let selection = match matches.value_of("selection") {
+            Some("primary") => Selection::Primary,
+            Some("both") => Selection::Both,
+            _ => Selection::Clipboard,
+        };
+
+        ClipboardOptions {
+            selection,
+            clear_after,
+            osc52: matches.is_present("osc52") || osc52_is_needed(),
+        }
+    }
+}
+
+/// What `copy_to_clipboard` did, to tell the user about it
+pub struct Copied {
+    pub selection: Selection,
+    /// Number of seconds after which the value will be cleared, if it will be
+    pub cleared_after: Option<u64>,
+}
+
+impl Copied {
+    /// Where the value was copied, ie: "clipboard"
+    pub fn target(&self) -> &'static str {
+        match self.selection {
+            Selection::Clipboard => "clipboard",
+            Selection::Primary => "primary selection",
+            Selection::Both => "clipboard and primary selection",
+        }
+    }
+
+    /// How to paste the value, ie: "Ctrl+V"
+    pub fn paste_keys(&self) -> &'static str {
+        match self.selection {
+            Selection::Clipboard => paste_keys(),
+            Selection::Primary => "a middle click",
+            Selection::Both if cfg!(target_os = "macos") => "Cmd+V or a middle click",
+            Selection::Both => "Ctrl+V or a middle click",
+        }
+    }
+
+    /// Tells how long the value stays there, ie: " It will be cleared in 30 seconds."
+    pub fn clear_notice(&self) -> String {
+        match self.cleared_after {
+            Some(seconds) => format!(" It will be cleared in {} seconds.", seconds),
+            None => String::new(),
+        }
+    }
+}
+
+/// Over SSH without a display server, there is no clipboard to copy to on this machine, but
+/// the terminal on the other end has one.
+fn osc52_is_needed() -> bool {
+    use std::env::var_os;
+
+    var_os("SSH_TTY").is_some()
+        && var_os("DISPLAY").is_none()
+        && var_os("WAYLAND_DISPLAY").is_none()
+}
+
+/// The escape sequence that asks the terminal to put a value in its clipboard
+fn osc52_sequence(value: &SafeString, selection: Selection) -> SafeString {
+    let target = match selection {
+        Selection::Clipboard => "c",
+        Selection::Primary => "p",
+        Selection::Both => "pc",
+    };
+    let encoded = SafeString::from_string(openssl::base64::encode_block(value.as_bytes()));
+    SafeString::from_string(format!("\x1b]52;{};{}\x07", target, encoded.deref()))
+}
+
+/// Copies a value to the clipboard
+pub fn copy_to_clipboard(s: &SafeString, options: &ClipboardOptions) -> Result<Copied, ()> {
+    if options.osc52 {
+        // Terminals don't reliably tell what their clipboard holds, so it is never cleared.
+        print_tty(osc52_sequence(s, options.selection).deref()).map_err(|_| ())?;
+        return Ok(Copied {
+            selection: options.selection,
+            cleared_after: None,
+        });
+    }
+
+    copy_to_system_clipboard(s, options)
+}
 
 // On Windows and Mac, we'll use the native solutions provided by the OS libraries
+//
+// There is no primary selection and the clipboard is not cleared automatically there, so
+// `options.selection` and `options.clear_after` are ignored.
 #[cfg(any(windows, target_os = "macos"))]
-pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
+fn copy_to_system_clipboard(s: &SafeString, _options: &ClipboardOptions) -> Result<Copied, ()> {
     use clipboard::ClipboardContext;
     use clipboard::ClipboardProvider;
 
     let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
     context.set_contents(s.deref().to_owned()).map_err(|_| ())?;
-    Ok(())
+    Ok(Copied {
+        selection: Selection::Clipboard,
+        cleared_after: None,
+    })
 }
 
-// On UNIX, the most stable way to copy to the clipboard is using one of the existing
-// and battle tested tools: xsel and xclip.
+/// A command line tool that copies what it reads on its standard input to the clipboard, like
+/// `xsel -ib` or `pbcopy`
+///
+/// The secret is written to the standard input of the tool, so that it never appears in the
+/// arguments of a process, where anyone could see it with `ps`.
 #[cfg(all(unix, not(target_os = "macos")))]
-pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
-    use crate::quale::which;
-    use crate::shell_escape;
-    use std::env;
-    use std::process::Command;
-
-    let password = SafeString::from_string(shell_escape::escape(s.deref().into()).into());
-
-    fn wayland_clipboards(password: &SafeString) -> Result<(), ()> {
-        match which("wl-copy") {
-            Some(wl_copy) => {
-                let shell = format!(
-                    "printf '%s' {} | {} 2> /dev/null",
-                    password.deref(),
-                    wl_copy.to_string_lossy()
-                );
-                if Command::new("sh")
-                    .args(&["-c", shell.as_str()])
-                    .status()
-                    .map_err(|_| ())?
-                    .success()
-                {
-                    Ok(())
-                } else {
-                    Err(())
-                }
-            }
-            None => Err(()),
+struct Tool {
+    copy: Vec<OsString>,
+    /// The command that prints the clipboard, when there is one
+    paste: Option<Vec<OsString>>,
+    /// The command that clears the clipboard, or `None` to copy nothing instead
+    clear: Option<Vec<OsString>>,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command_line(program: PathBuf, args: &[&str]) -> Vec<OsString> {
+    let mut command_line = vec![program.into_os_string()];
+    command_line.extend(args.iter().map(OsString::from));
+    command_line
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn run(command_line: &[OsString], input: &[u8]) -> Result<(), ()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let (program, args) = command_line.split_first().ok_or(())?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| ())?;
+
+    // Dropping stdin closes it, so the tool knows it has everything.
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(input).is_ok(),
+        None => false,
+    };
+    let status = child.wait().map_err(|_| ())?;
+
+    if written && status.success() {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl Tool {
+    /// The tool set with `ROOSTER_CLIPBOARD_CMD`, ie: `tmux load-buffer -w -`
+    ///
+    /// Rooster can't read the clipboard with it, so it is never cleared automatically. It is
+    /// used for both selections, telling them apart is up to the user. Arguments are quoted like
+    /// in a shell, ie: `sh -c "xclip -sel c"`.
+    fn from_env() -> Option<Tool> {
+        let command = std::env::var("ROOSTER_CLIPBOARD_CMD").ok()?;
+        let copy: Vec<OsString> = split_words(&command)
+            .ok()?
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        if copy.is_empty() {
+            return None;
         }
+
+        Some(Tool {
+            copy,
+            paste: None,
+            clear: None,
+        })
     }
 
-    fn x11_clipboards(password: &SafeString) -> Result<(), ()> {
-        match which("xsel") {
-            Some(xsel) => {
-                let shell = format!(
-                    "printf '%s' {} | {} -ib 2> /dev/null",
-                    password.deref(),
-                    xsel.to_string_lossy()
-                );
-                if Command::new("sh")
-                    .args(&["-c", shell.as_str()])
-                    .status()
-                    .map_err(|_| ())?
-                    .success()
-                {
-                    Ok(())
-                } else {
-                    Err(())
+    /// The tools found on the system for a selection, in the order they should be tried
+    ///
+    /// `selection` is either the clipboard or the primary selection, not both.
+    fn find_all(selection: Selection) -> Vec<Tool> {
+        use crate::quale::which;
+
+        let primary = selection == Selection::Primary;
+        let mut tools = vec![];
+
+        if let Some(s) = std::env::var_os("XDG_SESSION_TYPE") {
+            if s == "wayland" {
+                if let Some(wl_copy) = which("wl-copy") {
+                    let flags: &[&str] = if primary { &["--primary"] } else { &[] };
+                    tools.push(Tool {
+                        copy: command_line(wl_copy.clone(), flags),
+                        paste: which("wl-paste").map(|wl_paste| {
+                            command_line(wl_paste, &[flags, &["--no-newline"]].concat())
+                        }),
+                        clear: Some(command_line(wl_copy, &[flags, &["--clear"]].concat())),
+                    });
                 }
             }
-            None => match which("xclip") {
-                Some(xclip) => {
-                    let shell = format!(
-                        "printf '%s' {} | {} -selection clipboard 2> /dev/null",
-                        password.deref(),
-                        xclip.to_string_lossy()
-                    );
-                    if Command::new("sh")
-                        .args(&["-c", shell.as_str()])
-                        .status()
-                        .map_err(|_| ())?
-                        .success()
-                    {
-                        Ok(())
-                    } else {
-                        Err(())
-                    }
-                }
-                None => Err(()),
-            },
         }
+
+        if let Some(xsel) = which("xsel") {
+            let flag = if primary { "-p" } else { "-b" };
+            tools.push(Tool {
+                copy: command_line(xsel.clone(), &["-i", flag]),
+                paste: Some(command_line(xsel.clone(), &["-o", flag])),
+                clear: Some(command_line(xsel, &["-c", flag])),
+            });
+        }
+
+        if let Some(xclip) = which("xclip") {
+            let name = if primary { "primary" } else { "clipboard" };
+            tools.push(Tool {
+                copy: command_line(xclip.clone(), &["-selection", name]),
+                paste: Some(command_line(xclip, &["-selection", name, "-o"])),
+                clear: None,
+            });
+        }
+
+        // pbcopy only knows about the clipboard.
+        if primary {
+            return tools;
+        }
+
+        if let Some(pbcopy) = which("pbcopy") {
+            tools.push(Tool {
+                copy: command_line(pbcopy, &[]),
+                paste: which("pbpaste").map(|pbpaste| command_line(pbpaste, &[])),
+                clear: None,
+            });
+        }
+
+        tools
     }
 
-    match env::var_os("XDG_SESSION_TYPE") {
-        Some(s) if s == "wayland" => {
-            let s = wayland_clipboards(&password);
-            match s {
-                Ok(_) => Ok(()),
-                Err(_) => x11_clipboards(&password),
+    fn copy(&self, value: &[u8]) -> Result<(), ()> {
+        run(&self.copy, value)
+    }
+
+    /// Clears the clipboard after some time, from a detached process, so that Rooster itself
+    /// does not have to wait
+    ///
+    /// The clipboard is only cleared if it still holds `value`: if the user copied something
+    /// else in the meantime, we leave it alone.
+    ///
+    /// The helper is a shell that gets the value on its standard input. It is a new program,
+    /// so it holds neither the lock on the password file nor the decrypted passwords.
+    fn clear_later(&self, value: &SafeString, seconds: u64) -> Result<(), ()> {
+        use std::io::Write;
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        let paste = self.paste.as_ref().ok_or(())?;
+        let clear = self.clear.as_ref().unwrap_or(&self.copy);
+
+        // The subshell goes on in the background once the value is read, and is adopted by
+        // init. The "." keeps trailing newlines in the values that are compared.
+        let mut script = b"value=$(cat; echo .)\n(\n  sleep ".to_vec();
+        script.extend(seconds.to_string().as_bytes());
+        script.extend(b"\n  current=$(");
+        script.extend(shell_words(paste));
+        script.extend(b" </dev/null; echo .)\n  if [ \"$current\" = \"$value\" ]; then ");
+        script.extend(shell_words(clear));
+        script.extend(b" </dev/null; fi\n) </dev/null >/dev/null 2>&1 &\n");
+
+        // The tools are found already, but the shell still needs `cat` and `sleep`.
+        let mut path = std::env::var_os("PATH").unwrap_or_default();
+        path.push(":/usr/bin:/bin");
+
+        let mut command = Command::new("/bin/sh");
+        command
+            .arg("-c")
+            .arg(std::ffi::OsStr::from_bytes(&script))
+            .env("PATH", path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        // A new session, so that closing the terminal doesn't stop the helper.
+        unsafe {
+            command.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+        let mut child = command.spawn().map_err(|_| ())?;
+
+        let written = match child.stdin.take() {
+            Some(mut stdin) => stdin.write_all(value.as_bytes()).is_ok(),
+            None => false,
+        };
+        let status = child.wait().map_err(|_| ())?;
+        if written && status.success() {
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+}
+
+/// Quotes a command line for the shell, ie: `'/usr/bin/xsel' '-o' '-b'`
+#[cfg(all(unix, not(target_os = "macos")))]
+fn shell_words(command_line: &[OsString]) -> Vec<u8> {
+    let mut words = Vec::new();
+    for (i, arg) in command_line.iter().enumerate() {
+        if i > 0 {
+            words.push(b' ');
+        }
+        words.push(b'\'');
+        for byte in arg.as_bytes() {
+            if *byte == b'\'' {
+                words.extend(b"'\\''");
+            } else {
+                words.push(*byte);
             }
         }
-        _ => x11_clipboards(&password),
+        words.push(b'\'');
+    }
+    words
+}
+
+// On UNIX, the most stable way to copy to the clipboard is using one of the existing
+// and battle tested tools: wl-copy, xsel and xclip. `ROOSTER_CLIPBOARD_CMD` can be set to
+// use any other tool that reads from its standard input.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn copy_to_system_clipboard(s: &SafeString, options: &ClipboardOptions) -> Result<Copied, ()> {
+    let mut cleared = true;
+
+    for selection in options.selection.parts() {
+        let tools = match Tool::from_env() {
+            Some(tool) => vec![tool],
+            None => Tool::find_all(*selection),
+        };
+
+        let tool = tools
+            .into_iter()
+            .find(|tool| tool.copy(s.as_bytes()).is_ok())
+            .ok_or(())?;
+
+        // Without a way to read the clipboard, we can't tell whether it still holds the value.
+        match options.clear_after {
+            Some(seconds) if tool.clear_later(s, seconds).is_ok() => {}
+            _ => cleared = false,
+        }
     }
+
+    Ok(Copied {
+        selection: options.selection,
+        cleared_after: options.clear_after.filter(|_| cleared),
+    })
 }
 
 #[cfg(target_os = "macos")]
@@ -115,7 +418,8 @@ pub fn paste_keys() -> &'static str {
 
 pub fn confirm_password_retrieved(
     show: bool,
-    password: &password::v2::Password,
+    password: &password::v3::Password,
+    options: &ClipboardOptions,
     io: &mut impl CliInputOutput,
 ) {
     if show {
@@ -131,32 +435,167 @@ pub fn confirm_password_retrieved(
             format!("Password: {}", password.password.deref()),
             OutputType::Standard,
         );
+        show_details(password, io);
     } else {
-        if copy_to_clipboard(&password.password).is_err() {
+        match copy_to_clipboard(&password.password, options) {
+            Err(()) => {
+                io.success(
+                    format!(
+                        "Hmm, I tried to copy your new password to your clipboard, but \
+                         something went wrong. You can see it with `rooster get '{}' --show`",
+                        password.name
+                    ),
+                    OutputType::Standard,
+                );
+            }
+            Ok(copied) => {
+                io.success(
+                    format!("Alright! Here is your password for {}:", password.name),
+                    OutputType::Standard,
+                );
+                io.success(
+                    format!("Username: {}", password.username),
+                    OutputType::Standard,
+                );
+                io.success(
+                    format!(
+                        "Password: ******** (copied to {}, paste with {}){}",
+                        copied.target(),
+                        copied.paste_keys(),
+                        copied.clear_notice()
+                    ),
+                    OutputType::Standard,
+                );
+                show_details(password, io);
+            }
+        }
+    }
+}
+
+/// Shows the URL, notes, tags and custom fields of a password, but not the secret fields
+fn show_details(password: &password::v3::Password, io: &mut impl CliInputOutput) {
+    if let Some(url) = &password.url {
+        io.success(format!("URL: {}", url), OutputType::Standard);
+    }
+    if let Some(notes) = &password.notes {
+        io.success(format!("Notes: {}", notes), OutputType::Standard);
+    }
+    if !password.tags.is_empty() {
+        io.success(
+            format!("Tags: {}", password.tags.join(", ")),
+            OutputType::Standard,
+        );
+    }
+    if password.otp.is_some() {
+        io.success(
+            format!(
+                "One-time codes: see them with `rooster otp '{}'`",
+                password.name
+            ),
+            OutputType::Standard,
+        );
+    }
+    for field in password.fields.iter() {
+        if field.secret {
             io.success(
                 format!(
-                    "Hmm, I tried to copy your new password to your clipboard, but \
-                     something went wrong. You can see it with `rooster get '{}' --show`",
-                    password.name
+                    "{}: ******** (see it with `--field '{}'`)",
+                    field.name, field.name
                 ),
                 OutputType::Standard,
             );
         } else {
             io.success(
-                format!("Alright! Here is your password for {}:", password.name),
-                OutputType::Standard,
-            );
-            io.success(
-                format!("Username: {}", password.username),
-                OutputType::Standard,
-            );
-            io.success(
-                format!(
-                    "Password: ******** (copied to clipboard, paste with {})",
-                    paste_keys()
-                ),
+                format!("{}: {}", field.name, field.value.deref()),
                 OutputType::Standard,
             );
         }
     }
 }
+
+pub fn confirm_field_retrieved(
+    show: bool,
+    password: &password::v3::Password,
+    field_name: &str,
+    value: &SafeString,
+    options: &ClipboardOptions,
+    io: &mut impl CliInputOutput,
+) {
+    if show {
+        io.success(
+            format!(
+                "Alright! Here is your {} for {}:",
+                field_name, password.name
+            ),
+            OutputType::Standard,
+        );
+        io.success(value.deref(), OutputType::Standard);
+    } else {
+        match copy_to_clipboard(value, options) {
+            Err(()) => {
+                io.success(
+                    format!(
+                        "Hmm, I tried to copy your {} to your clipboard, but something went \
+                         wrong. You can see it with `rooster get '{}' --field '{}' --show`",
+                        field_name, password.name, field_name
+                    ),
+                    OutputType::Standard,
+                );
+            }
+            Ok(copied) => {
+                io.success(
+                    format!(
+                        "Alright! I've copied your {} for {} to your {}, paste it with {}.{}",
+                        field_name,
+                        password.name,
+                        copied.target(),
+                        copied.paste_keys(),
+                        copied.clear_notice()
+                    ),
+                    OutputType::Standard,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{osc52_sequence, Selection};
+    use std::ops::Deref;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(
+            osc52_sequence(&"abcd".into(), Selection::Clipboard).deref(),
+            "\u{1b}]52;c;YWJjZA==\u{7}"
+        );
+        assert_eq!(
+            osc52_sequence(&"abcd".into(), Selection::Primary).deref(),
+            "\u{1b}]52;p;YWJjZA==\u{7}"
+        );
+        assert_eq!(
+            osc52_sequence(&"abcd".into(), Selection::Both).deref(),
+            "\u{1b}]52;pc;YWJjZA==\u{7}"
+        );
+    }
+
+    #[test]
+    fn test_osc52_sequence_payload() {
+        // The payload must never hold the escape characters that would end the sequence early
+        let value = "p\u{7}ss\u{1b}wörd;\n";
+        let sequence = osc52_sequence(&value.into(), Selection::Clipboard);
+        let payload = sequence
+            .deref()
+            .strip_prefix("\u{1b}]52;c;")
+            .and_then(|rest| rest.strip_suffix('\u{7}'))
+            .unwrap();
+        assert!(payload
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+/=".contains(c)));
+        assert_eq!(
+            openssl::base64::decode_block(payload).unwrap(),
+            value.as_bytes()
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
+use crate::clip::{copy_to_clipboard, ClipboardOptions};
+use crate::details;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
@@ -6,13 +8,14 @@ use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let app_name = matches.value_of("app").unwrap();
     let username = matches.value_of("username").unwrap();
 
     if store.has_password(app_name.deref()) {
+        output::remember_error(&password::PasswordError::AppExistsError);
         io.error(
             "Woops, there is already an app with that name.",
             OutputType::Error,
@@ -23,8 +26,9 @@ pub fn callback_exec(
     match io.prompt_password(format!("What password do you want for \"{}\"? ", app_name)) {
         Ok(password_as_string) => {
             let password_as_string_clipboard = password_as_string.clone();
-            let password =
-                password::v2::Password::new(app_name.clone(), username, password_as_string);
+            let mut password =
+                password::v3::Password::new(app_name.clone(), username, password_as_string);
+            details::apply_details(matches, &mut password);
             match store.add_password(password) {
                 Ok(_) => {
                     if matches.is_present("show") {
@@ -38,28 +42,34 @@ pub fn callback_exec(
                         return Ok(());
                     }
 
-                    if copy_to_clipboard(&password_as_string_clipboard).is_err() {
-                        io.success(
-                            format!(
-                                "Hmm, I tried to copy your new password to your clipboard, \
-                                 but something went wrong. Don't worry, it's saved, and you \
-                                 can see it with `rooster get {} --show`",
-                                app_name
-                            ),
-                            OutputType::Standard,
-                        );
-                    } else {
-                        io.success(
-                            format!(
-                                "Alright! I've saved your new password. You can paste it \
-                                 anywhere with {}.",
-                                paste_keys()
-                            ),
-                            OutputType::Standard,
-                        );
+                    let clipboard_options = ClipboardOptions::from_matches(matches);
+                    match copy_to_clipboard(&password_as_string_clipboard, &clipboard_options) {
+                        Err(()) => {
+                            io.success(
+                                format!(
+                                    "Hmm, I tried to copy your new password to your clipboard, \
+                                     but something went wrong. Don't worry, it's saved, and you \
+                                     can see it with `rooster get {} --show`",
+                                    app_name
+                                ),
+                                OutputType::Standard,
+                            );
+                        }
+                        Ok(copied) => {
+                            io.success(
+                                format!(
+                                    "Alright! I've saved your new password. You can paste it \
+                                     anywhere with {}.{}",
+                                    copied.paste_keys(),
+                                    copied.clear_notice()
+                                ),
+                                OutputType::Standard,
+                            );
+                        }
                     }
                 }
                 Err(err) => {
+                    output::remember_error(&err);
                     io.error(
                         format!("Woops, I couldn't add the password (reason: {:?}).", err),
                         OutputType::Error,
@@ -0,0 +1,52 @@
+#[cfg(unix)]
+use crate::agent;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+#[cfg(unix)]
+use std::time::Duration;
+
+#[cfg(unix)]
+pub fn callback_exec(matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    let timeout = match matches.value_of("timeout") {
+        Some(seconds) => Duration::from_secs(seconds.parse::<u64>().map_err(|_| {
+            io.error("Woops, the timeout is too big.", OutputType::Error);
+            1
+        })?),
+        None => agent::DEFAULT_TIMEOUT,
+    };
+
+    let mut agent = agent::Agent::bind(timeout).map_err(|err| {
+        io.error(
+            format!("Woops, I could not start the agent (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    io.success(
+        format!(
+            "The agent is listening on {}. It forgets your master password after {} seconds \
+             without use, or right away with `rooster lock`.",
+            agent.path().to_string_lossy(),
+            timeout.as_secs()
+        ),
+        OutputType::Standard,
+    );
+
+    agent.run().map_err(|err| {
+        io.error(
+            format!("Woops, the agent stopped (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+#[cfg(not(unix))]
+pub fn callback_exec(_matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    io.error(
+        "Woops, the agent is only available on Unix.",
+        OutputType::Error,
+    );
+    Err(1)
+}
@@ -1,13 +1,15 @@
 use crate::clip;
+use crate::details;
 use crate::ffi;
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let query = matches.value_of("app").unwrap();
@@ -22,30 +24,45 @@ pub fn callback_exec(
     .ok_or(1)?
     .clone();
 
-    let password_as_string = io
-        .prompt_password(format!(
-            "What password do you want for \"{}\"? ",
-            password.name
-        ))
-        .map_err(|err| {
-            io.error(
-                format!("\nI couldn't read the app's password (reason: {:?}).", err),
-                OutputType::Error,
-            );
-            1
-        })?;
+    // When only the details are changed, the password itself stays the same.
+    let password_as_string = if details::has_details(matches) || matches.is_present("remove-field")
+    {
+        None
+    } else {
+        Some(
+            io.prompt_password(format!(
+                "What password do you want for \"{}\"? ",
+                password.name
+            ))
+            .map_err(|err| {
+                io.error(
+                    format!("\nI couldn't read the app's password (reason: {:?}).", err),
+                    OutputType::Error,
+                );
+                1
+            })?,
+        )
+    };
 
     let password = store
-        .change_password(&password.name, &|old_password: password::v2::Password| {
-            password::v2::Password {
-                name: old_password.name,
-                username: old_password.username,
-                password: password_as_string.clone(),
-                created_at: old_password.created_at,
+        .change_password(&password.name, &|old_password: password::v3::Password| {
+            let mut new_password = password::v3::Password {
+                password: password_as_string
+                    .clone()
+                    .unwrap_or_else(|| old_password.password.clone()),
                 updated_at: ffi::time(),
+                ..old_password
+            };
+            if let Some(names) = matches.values_of("remove-field") {
+                for name in names {
+                    new_password.remove_field(name);
+                }
             }
+            details::apply_details(matches, &mut new_password);
+            new_password
         })
         .map_err(|err| {
+            output::remember_error(&err);
             io.error(
                 format!(
                     "Woops, I couldn't save the new password (reason: {:?}).",
@@ -57,6 +74,11 @@ pub fn callback_exec(
         })?;
 
     let show = matches.is_present("show");
-    clip::confirm_password_retrieved(show, &password, io);
+    clip::confirm_password_retrieved(
+        show,
+        &password,
+        &clip::ClipboardOptions::from_matches(matches),
+        io,
+    );
     Ok(())
 }
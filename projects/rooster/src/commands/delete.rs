@@ -1,11 +1,12 @@
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let query = matches.value_of("app").unwrap();
@@ -21,6 +22,7 @@ pub fn callback_exec(
     .clone();
 
     if let Err(err) = store.delete_password(&password.name) {
+        output::remember_error(&err);
         io.error(
             format!(
                 "Woops, I couldn't delete this password (reason: {:?}).",
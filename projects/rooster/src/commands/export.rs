@@ -1,5 +1,8 @@
+use crate::bundle;
+use crate::ffi;
+use crate::list;
 use crate::password;
-use crate::password::v2::Password;
+use crate::password::v3::Password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 use crate::rutil::safe_string::SafeString;
@@ -16,7 +19,7 @@ pub struct JsonExport {
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let subcommand_name = matches.subcommand_name().unwrap();
@@ -27,7 +30,9 @@ pub fn callback_exec(
     } else if subcommand_name == "csv" {
         export_to_csv(subcommand_matches, store, io)
     } else if subcommand_name == "1password" {
-        export_to_csv(subcommand_matches, store, io)
+        export_to_1password(subcommand_matches, store, io)
+    } else if subcommand_name == "rooster-bundle" {
+        export_to_rooster_bundle(subcommand_matches, store, io)
     } else {
         unimplemented!("Invalid export destination")
     }
@@ -35,17 +40,32 @@ pub fn callback_exec(
 
 fn export_to_csv(
     _matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let passwords_ref = store.get_all_passwords();
     let output_cursor: Cursor<Vec<u8>> = Cursor::new(Vec::new());
     let mut csv_writer = Writer::from_writer(output_cursor);
     for password in passwords_ref {
+        let fields = if password.fields.is_empty() {
+            String::new()
+        } else {
+            match serde_json::to_string(&password.fields) {
+                Ok(fields) => fields,
+                Err(_) => return Err(1),
+            }
+        };
+        let fields = SafeString::from_string(fields);
         match csv_writer.write_record(&[
             &password.name,
             &password.username,
             password.password.deref().as_str(),
+            password.url.as_deref().unwrap_or(""),
+            password.notes.as_deref().unwrap_or(""),
+            password.tags.join(",").as_str(),
+            fields.deref().as_str(),
+            password.created_at.to_string().as_str(),
+            password.updated_at.to_string().as_str(),
         ]) {
             Ok(_) => {}
             Err(_) => return Err(1),
@@ -59,9 +79,45 @@ fn export_to_csv(
     return Ok(());
 }
 
+/// The columns 1Password reads from a CSV file, with a header row so that they are matched by name
+///
+/// Only logins are exported, tags and extra fields have no column.
+fn export_to_1password(
+    _matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let output_cursor: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+    let mut csv_writer = Writer::from_writer(output_cursor);
+    if csv_writer
+        .write_record(["Title", "Website", "Username", "Password", "Notes", "Type"])
+        .is_err()
+    {
+        return Err(1);
+    }
+    for password in store.get_all_passwords() {
+        match csv_writer.write_record([
+            password.name.as_str(),
+            password.url.as_deref().unwrap_or(""),
+            password.username.as_str(),
+            password.password.deref().as_str(),
+            password.notes.as_deref().unwrap_or(""),
+            "Login",
+        ]) {
+            Ok(_) => {}
+            Err(_) => return Err(1),
+        }
+    }
+    let output = SafeString::from_string(
+        String::from_utf8(csv_writer.into_inner().unwrap().into_inner()).unwrap(),
+    );
+    io.write(output.deref().as_str(), OutputType::Standard);
+    Ok(())
+}
+
 fn export_to_json(
     _matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let export = JsonExport {
@@ -89,3 +145,99 @@ fn export_to_json(
     io.write(format!("{}", passwords.deref()), OutputType::Standard);
     return Ok(());
 }
+
+fn ask_recipient_password(io: &mut impl CliInputOutput) -> Result<SafeString, i32> {
+    let read_error = |err| {
+        format!(
+            "I could not read the password of the bundle (reason: {:?}).",
+            err
+        )
+    };
+    let recipient_password =
+        match io.prompt_password("Type a password to share with the recipient: ") {
+            Ok(password) => password,
+            Err(err) => {
+                io.error(read_error(err), OutputType::Error);
+                return Err(1);
+            }
+        };
+    let confirmation = match io.prompt_password("Type it once more: ") {
+        Ok(password) => password,
+        Err(err) => {
+            io.error(read_error(err), OutputType::Error);
+            return Err(1);
+        }
+    };
+
+    if recipient_password.is_empty() {
+        io.error(
+            "The password of the bundle can't be empty. Aborting.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+    if recipient_password != confirmation {
+        io.error(
+            "The password confirmation did not match. Aborting.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+    Ok(recipient_password)
+}
+
+fn export_to_rooster_bundle(
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let filter = list::Filter {
+        query: matches.value_of("query").map(|query| query.to_owned()),
+        username: None,
+        tags: matches
+            .values_of("tag")
+            .map(|tags| tags.map(|tag| tag.to_lowercase()).collect())
+            .unwrap_or_default(),
+        older_than: None,
+        sort: None,
+    };
+    let passwords: Vec<Password> = filter
+        .apply(store, ffi::time())
+        .into_iter()
+        .map(|password| Password {
+            // The recipient only needs the current passwords
+            history: Vec::new(),
+            ..password.clone()
+        })
+        .collect();
+
+    if passwords.is_empty() {
+        io.error(
+            "Woops, no passwords match, there is nothing to export.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    // The bundle goes to the standard output, so this can be checked before sending it.
+    let names: Vec<&str> = passwords.iter().map(|p| p.name.as_str()).collect();
+    io.info(
+        format!("Exporting {}: {}", names.len(), names.join(", ")),
+        OutputType::Error,
+    );
+
+    let recipient_password = ask_recipient_password(io)?;
+    match bundle::seal(passwords, recipient_password) {
+        Ok(bundle) => {
+            io.write(bundle, OutputType::Standard);
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I could not encrypt the bundle (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
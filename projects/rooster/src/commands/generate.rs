@@ -1,20 +1,23 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
-use crate::generate::{check_password_len, PasswordSpec};
+use crate::clip::{copy_to_clipboard, ClipboardOptions};
+use crate::generate::{report_entropy, PasswordSpec};
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use serde_json::json;
 
 use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let app_name = matches.value_of("app").unwrap();
     let username = matches.value_of("username").unwrap();
 
     if store.has_password(app_name.deref()) {
+        output::remember_error(&password::PasswordError::AppExistsError);
         io.error(
             "Woops, there is already an app with that name.",
             OutputType::Error,
@@ -22,12 +25,7 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    let pwspec = PasswordSpec::new(
-        matches.is_present("alnum"),
-        matches
-            .value_of("length")
-            .and_then(|len| check_password_len(len.parse::<usize>().ok(), io)),
-    );
+    let pwspec = PasswordSpec::from_matches(matches, None, io)?;
 
     let password_as_string = match pwspec.generate_hard_password() {
         Ok(password_as_string) => password_as_string,
@@ -45,10 +43,34 @@ pub fn callback_exec(
 
     // Read the master password and try to save the new password.
     let password_as_string_clipboard = password_as_string.clone();
-    let password = password::v2::Password::new(app_name.clone(), username, password_as_string);
+    let mut password = password::v3::Password::new(app_name, username, password_as_string);
+    if matches.is_present("policy") {
+        password.policy = Some(pwspec.policy.clone());
+    }
 
     match store.add_password(password) {
         Ok(_) => {
+            report_entropy(&pwspec, io);
+
+            if output::Format::from_matches(matches) == output::Format::Json {
+                let mut document = json!({
+                    "name": app_name,
+                    "username": username,
+                    "entropy_bits": pwspec.entropy().round(),
+                });
+                if matches.is_present("show") {
+                    document["password"] = json!(password_as_string_clipboard.deref());
+                } else {
+                    let clipboard_options = ClipboardOptions::from_matches(matches);
+                    let copied =
+                        copy_to_clipboard(&password_as_string_clipboard, &clipboard_options)
+                            .is_ok();
+                    document["copied"] = json!(copied);
+                }
+                output::print_json(&document, io);
+                return Ok(());
+            }
+
             if matches.is_present("show") {
                 io.success(
                     format!(
@@ -60,29 +82,36 @@ pub fn callback_exec(
                 return Ok(());
             }
 
-            if copy_to_clipboard(&password_as_string_clipboard).is_err() {
-                io.success(
-                    format!(
-                        "Hmm, I tried to copy your new password to your clipboard, but \
-                         something went wrong. Don't worry, it's saved, and you can see it \
-                         with `rooster get {} --show`",
-                        app_name
-                    ),
-                    OutputType::Standard,
-                );
-            } else {
-                io.success(
-                    format!(
-                        "Alright! I've saved your new password. You can paste it anywhere with {}.",
-                        paste_keys()
-                    ),
-                    OutputType::Standard,
-                );
+            let clipboard_options = ClipboardOptions::from_matches(matches);
+            match copy_to_clipboard(&password_as_string_clipboard, &clipboard_options) {
+                Err(()) => {
+                    io.success(
+                        format!(
+                            "Hmm, I tried to copy your new password to your clipboard, but \
+                             something went wrong. Don't worry, it's saved, and you can see it \
+                             with `rooster get {} --show`",
+                            app_name
+                        ),
+                        OutputType::Standard,
+                    );
+                }
+                Ok(copied) => {
+                    io.success(
+                        format!(
+                            "Alright! I've saved your new password. You can paste it anywhere \
+                             with {}.{}",
+                            copied.paste_keys(),
+                            copied.clear_notice()
+                        ),
+                        OutputType::Standard,
+                    );
+                }
             }
 
             Ok(())
         }
         Err(err) => {
+            output::remember_error(&err);
             io.error(
                 format!("\nI couldn't add this password (reason: {:?}).", err),
                 OutputType::Error,
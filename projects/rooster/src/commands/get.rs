@@ -1,15 +1,107 @@
 use crate::clip;
-
+use crate::ffi;
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::safe_string::SafeString;
+use serde_json::json;
+use std::ops::Deref;
+
+/// Copies a value for `--sequence`, stopping the sequence if it didn't work
+fn copy_in_sequence(
+    password: &password::v3::Password,
+    what: &str,
+    value: &SafeString,
+    options: &clip::ClipboardOptions,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    match clip::copy_to_clipboard(value, options) {
+        Ok(copied) => {
+            io.success(
+                format!(
+                    "Alright! I've copied your {} for {} to your {}, paste it with {}.{}",
+                    what,
+                    password.name,
+                    copied.target(),
+                    copied.paste_keys(),
+                    copied.clear_notice()
+                ),
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(()) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't copy your {} to your clipboard. You can see it with \
+                     `rooster get '{}' --show`",
+                    what, password.name
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+/// Waits for the user to hit ENTER before copying the next value
+fn wait_for_enter(what: &str, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    io.write(
+        format!("Hit ENTER to copy your {}. ", what),
+        OutputType::Standard,
+    );
+    io.read_line().map(|_| ()).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't read that (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+/// Copies the username, then the password and the one-time code, to fill login forms one
+/// field after the other
+fn copy_sequence(
+    password: &password::v3::Password,
+    options: &clip::ClipboardOptions,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    copy_in_sequence(
+        password,
+        "username",
+        &password.username.clone().into(),
+        options,
+        io,
+    )?;
+
+    wait_for_enter("password", io)?;
+    copy_in_sequence(password, "password", &password.password, options, io)?;
+
+    if let Some(otp) = &password.otp {
+        wait_for_enter("one-time code", io)?;
+        // Computed once ENTER is hit, so it's valid as long as possible.
+        let code = otp.code(ffi::time() as u64).map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't compute the code (reason: {}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+        copy_in_sequence(password, "one-time code", &code, options, io)?;
+    }
+
+    Ok(())
+}
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let show = matches.is_present("show");
+    let json = output::Format::from_matches(matches) == output::Format::Json;
     let query = matches.value_of("app").unwrap();
 
     let prompt = format!(
@@ -21,9 +113,78 @@ pub fn callback_exec(
         },
     );
     let password =
-        list::search_and_choose_password(store, query, list::WITH_NUMBERS, &prompt, io).ok_or(1)?;
+        list::search_and_pick_password(store, query, list::WITH_NUMBERS, &prompt, io).ok_or(1)?;
+
+    if matches.is_present("sequence") {
+        return copy_sequence(password, &clip::ClipboardOptions::from_matches(matches), io);
+    }
 
-    clip::confirm_password_retrieved(show, &password, io);
+    if let Some(field_name) = matches.value_of("field") {
+        let value = match password.get_field(field_name) {
+            Some(field) => Some(field.value.clone()),
+            // Custom fields take precedence, so they can be named like the built-in ones.
+            None => match field_name.to_lowercase().as_str() {
+                "username" => Some(password.username.clone().into()),
+                "url" => password.url.clone().map(|url| url.into()),
+                "notes" => password.notes.clone().map(|notes| notes.into()),
+                _ => None,
+            },
+        };
+
+        match value {
+            Some(value) if show && json => output::print_json(
+                &json!({
+                    "name": password.name,
+                    "field": field_name,
+                    "value": value.deref(),
+                }),
+                io,
+            ),
+            Some(value) => clip::confirm_field_retrieved(
+                show,
+                password,
+                field_name,
+                &value,
+                &clip::ClipboardOptions::from_matches(matches),
+                io,
+            ),
+            None => {
+                io.error(
+                    format!(
+                        "Woops, there is no field \"{}\" for {}.",
+                        field_name, password.name
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        }
+
+        return Ok(());
+    }
+
+    if show && json {
+        output::print_json(
+            &json!({
+                "name": password.name,
+                "username": password.username,
+                "password": password.password.deref(),
+                "url": password.url,
+                "notes": password.notes,
+                "tags": password.tags,
+                "fields": password.fields,
+            }),
+            io,
+        );
+        return Ok(());
+    }
+
+    clip::confirm_password_retrieved(
+        show,
+        password,
+        &clip::ClipboardOptions::from_matches(matches),
+        io,
+    );
 
     Ok(())
 }
@@ -0,0 +1,126 @@
+use crate::clip;
+use crate::list;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use std::ops::Deref;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.value_of("app").unwrap();
+    let show = matches.is_present("show");
+    let clipboard_options = clip::ClipboardOptions::from_matches(matches);
+
+    let password = list::search_and_pick_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password's history would you like to see?",
+        io,
+    )
+    .ok_or(1)?;
+
+    if password.history.is_empty() {
+        io.info(
+            format!("There are no previous passwords for {} yet.", password.name),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    // Copy or show a single version
+    if let Some(n) = matches.value_of("n") {
+        let previous = choose_version(password, n, io)?;
+
+        if show {
+            io.success(
+                format!(
+                    "Alright! Here is your previous password number {} for {}:",
+                    n, password.name
+                ),
+                OutputType::Standard,
+            );
+            io.success(previous.password.deref(), OutputType::Standard);
+        } else {
+            match clip::copy_to_clipboard(&previous.password, &clipboard_options) {
+                Err(()) => {
+                    io.success(
+                        format!(
+                            "Hmm, I tried to copy your previous password to your clipboard, but \
+                             something went wrong. You can see it with `rooster history '{}' {} --show`",
+                            password.name, n
+                        ),
+                        OutputType::Standard,
+                    );
+                }
+                Ok(copied) => {
+                    io.success(
+                        format!(
+                            "Alright! I've copied your previous password number {} for {} to your \
+                             {}, paste it with {}.{}",
+                            n,
+                            password.name,
+                            copied.target(),
+                            copied.paste_keys(),
+                            copied.clear_notice()
+                        ),
+                        OutputType::Standard,
+                    );
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    io.title(
+        format!("Previous passwords for {}", password.name),
+        OutputType::Standard,
+    );
+    let now = SystemTime::now();
+    let i_width = password.history.len().to_string().len();
+    for (i, previous) in password.history.iter().enumerate() {
+        let replaced_at = UNIX_EPOCH + Duration::from_secs(previous.replaced_at as u64);
+        io.info(
+            format!(
+                "{:i_width$} {} (replaced {})",
+                i + 1,
+                if show {
+                    previous.password.deref().as_str()
+                } else {
+                    "********"
+                },
+                list::format_age(replaced_at, now),
+                i_width = i_width,
+            ),
+            OutputType::Standard,
+        );
+    }
+
+    Ok(())
+}
+
+/// Finds a previous password from its number as typed by the user, 1 being the most recent
+pub fn choose_version<'a>(
+    password: &'a password::v3::Password,
+    n: &str,
+    io: &mut impl CliInputOutput,
+) -> Result<&'a password::v3::PreviousPassword, i32> {
+    match n.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= password.history.len() => Ok(&password.history[n - 1]),
+        _ => {
+            io.error(
+                format!(
+                    "Woops, I need a number between 1 and {} (see `rooster history '{}'`).",
+                    password.history.len(),
+                    password.name
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
@@ -1,89 +1,355 @@
-use crate::ffi;
+use crate::bundle;
+use crate::importers::csv_map::{self, Mapping};
+use crate::importers::{
+    self, check_password, csv_line, csv_record, parse_date, set_timestamps, split_tags, Imported,
+    Rejected,
+};
+use crate::list;
+use crate::output;
 use crate::password;
-use crate::password::v2::{Password, PasswordStore};
+use crate::password::v3::{Field, Password, PasswordStore};
 use crate::rclio::{CliInputOutput, OutputType};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json;
+use serde_json::json;
 use std::fs::File;
 
-#[derive(Serialize, Deserialize)]
-pub struct JsonExport {
-    passwords: Vec<Password>,
+/// A file generated with `rooster export json`
+///
+/// Passwords are read one by one, so that one that is invalid doesn't prevent importing the
+/// others.
+#[derive(Deserialize)]
+struct JsonImport {
+    passwords: Vec<serde_json::Value>,
 }
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let subcommand_name = matches.subcommand_name().unwrap();
     let subcommand_matches = matches.subcommand_matches(subcommand_name).unwrap();
 
-    let (valid, invalid) = if subcommand_name == "json" {
-        create_imported_passwords_from_json(subcommand_matches, io)
-    } else if subcommand_name == "csv" {
-        create_imported_passwords_from_csv(subcommand_matches, io)
-    } else if subcommand_name == "1password" {
-        create_imported_passwords_from_1password(subcommand_matches, io)
-    } else {
-        unimplemented!("Invalid import source")
+    let path = subcommand_matches.value_of("path").unwrap();
+    let (valid, invalid) = match subcommand_name {
+        "json" => create_imported_passwords_from_json(subcommand_matches, io),
+        "csv" => match subcommand_matches.value_of("map") {
+            // Validated by `clap` already
+            Some(map) => read_export(csv_map::read(path, &Mapping::parse(map).unwrap()), io),
+            None => create_imported_passwords_from_csv(subcommand_matches, io),
+        },
+        "1password" => create_imported_passwords_from_1password(subcommand_matches, io),
+        "rooster-bundle" => create_imported_passwords_from_rooster_bundle(subcommand_matches, io),
+        "bitwarden" => read_export(importers::bitwarden::read(path), io),
+        "keepass" => read_export(importers::keepass::read(path), io),
+        "lastpass" => read_export(csv_map::read(path, &Mapping::lastpass()), io),
+        "chrome" => read_export(csv_map::read(path, &Mapping::chrome()), io),
+        "firefox" => read_export(csv_map::read(path, &Mapping::firefox()), io),
+        "pass" => {
+            let decrypt_command = subcommand_matches.value_of("decrypt-command").unwrap();
+            read_export(importers::pass::read(path, decrypt_command), io)
+        }
+        _ => unimplemented!("Invalid import source"),
     }?;
 
-    import_passwords(valid, invalid, store, io)
+    import_passwords(
+        valid,
+        invalid,
+        store,
+        OnConflict::from_matches(subcommand_matches),
+        subcommand_matches.is_present("dry-run"),
+        output::Format::from_matches(matches),
+        io,
+    )
+}
+
+/// What to do with passwords that are already in the password store, set with `--on-conflict`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnConflict {
+    Skip,
+    Overwrite,
+    /// Overwrite if the imported password was updated more recently
+    Newer,
+    /// Import under another name, ie: "Youtube (2)"
+    Rename,
+    Ask,
+}
+
+impl OnConflict {
+    fn from_matches(matches: &clap::ArgMatches) -> OnConflict {
+        match matches.value_of("on-conflict") {
+            Some("overwrite") => OnConflict::Overwrite,
+            Some("newer") => OnConflict::Newer,
+            Some("rename") => OnConflict::Rename,
+            Some("ask") => OnConflict::Ask,
+            _ => OnConflict::Skip,
+        }
+    }
+}
+
+/// What happens to an imported password
+enum Action {
+    Add,
+    Skip(&'static str),
+    Overwrite,
+    Rename,
+}
+
+const SKIPPED_EXISTS: &str = "skipped (already in password store)";
+const SKIPPED_NOT_NEWER: &str = "skipped (not newer than the one in password store)";
+
+/// Says what happened to a password, or what would happen with `--dry-run`
+fn outcome(name: &str, what: &str, dry_run: bool) -> String {
+    if dry_run {
+        format!("{}, would be {}", name, what)
+    } else {
+        format!("{}, {}", name, what)
+    }
+}
+
+/// The first name that isn't taken yet, ie: "Youtube (2)" if there already is a "Youtube"
+fn free_name(store: &PasswordStore, name: &str) -> String {
+    (2..)
+        .map(|i| format!("{} ({})", name, i))
+        .find(|candidate| !store.has_password(candidate))
+        .unwrap()
+}
+
+fn describe_change(old: &str, new: &str) -> String {
+    if old == new {
+        format!("{} (unchanged)", old)
+    } else {
+        format!("{} -> {}", old, new)
+    }
+}
+
+/// Shows how the imported password differs from the one in the password store, and asks what
+/// to do with it
+fn ask(
+    existing: &Password,
+    imported: &Password,
+    io: &mut impl CliInputOutput,
+) -> Result<Action, i32> {
+    io.info(
+        format!("{} is already in the password store:", existing.name),
+        OutputType::Error,
+    );
+    io.info(
+        format!(
+            "  Username: {}",
+            describe_change(&existing.username, &imported.username)
+        ),
+        OutputType::Error,
+    );
+    // Passwords are never shown, but it helps to know whether they differ.
+    io.info(
+        if existing.password == imported.password {
+            "  Password: ******** (unchanged)"
+        } else {
+            "  Password: ******** -> ******** (changed)"
+        },
+        OutputType::Error,
+    );
+    io.info(
+        format!(
+            "  Updated:  {}",
+            describe_change(
+                &list::format_date(existing.updated_at),
+                &list::format_date(imported.updated_at)
+            )
+        ),
+        OutputType::Error,
+    );
+
+    loop {
+        io.info("Skip, overwrite or rename? [s/o/r]", OutputType::Error);
+        match io.read_line() {
+            Ok(line) => match line.trim().to_lowercase().chars().next() {
+                Some('s') => return Ok(Action::Skip(SKIPPED_EXISTS)),
+                Some('o') => return Ok(Action::Overwrite),
+                Some('r') => return Ok(Action::Rename),
+                _ => io.info("I did not get that.", OutputType::Error),
+            },
+            Err(io_err) => {
+                io.error(
+                    format!(
+                        "Woops, an error occured while reading your response (reason: {:?}).",
+                        io_err
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        }
+    }
 }
 
 fn import_passwords(
     valid: Vec<Password>,
-    invalid: Vec<Password>,
+    invalid: Vec<Rejected>,
     store: &mut PasswordStore,
+    on_conflict: OnConflict,
+    dry_run: bool,
+    format: output::Format,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
+    // A dry run works on a copy of the password store, which is never saved.
+    let mut copy;
+    let store = if dry_run {
+        copy = store.clone();
+        &mut copy
+    } else {
+        store
+    };
+
     let mut errors = 0;
     let mut warnings = 0;
     let mut successes = 0;
-    for password in invalid {
-        io.error(
-            format!("{}, invalid format, skipping", password.name),
-            OutputType::Error,
-        );
+    let mut overwritten = 0;
+    // What went wrong for each password, for the JSON output
+    let mut problems = Vec::new();
+    for rejected in invalid.iter() {
+        problems.push(json!({
+            "name": rejected.name,
+            "line": rejected.line,
+            "level": "error",
+            "code": "invalid_format",
+            "reason": rejected.reason,
+        }));
         errors += 1;
     }
-    for password in valid {
-        if let Some(_) = store.get_password(&password.name) {
-            io.warning(
-                format!("{}, already in password store, skipping", password.name),
-                OutputType::Error,
-            );
-            warnings += 1;
-            continue;
-        }
+    for mut password in valid {
+        let name = password.name.clone();
+        let action = match store.get_password(&name) {
+            None => Action::Add,
+            Some(existing) => match on_conflict {
+                OnConflict::Skip => Action::Skip(SKIPPED_EXISTS),
+                OnConflict::Overwrite => Action::Overwrite,
+                OnConflict::Newer if password.updated_at > existing.updated_at => Action::Overwrite,
+                OnConflict::Newer => Action::Skip(SKIPPED_NOT_NEWER),
+                OnConflict::Rename => Action::Rename,
+                OnConflict::Ask => ask(&existing, &password, io)?,
+            },
+        };
 
-        if let Err(err) = store.add_password(password.clone()) {
-            io.error(
-                format!("{}, error ({:?})", password.name, err),
-                OutputType::Error,
-            );
-            errors += 1;
-            continue;
+        let result = match action {
+            Action::Skip(reason) => {
+                io.warning(outcome(&name, reason, dry_run), OutputType::Error);
+                problems.push(json!({
+                    "name": name,
+                    "level": "warning",
+                    "code": password::PasswordError::AppExistsError.code(),
+                }));
+                warnings += 1;
+                continue;
+            }
+            Action::Add => store.add_password(password).map(|_| {
+                if dry_run {
+                    Some(String::from("imported"))
+                } else {
+                    None
+                }
+            }),
+            Action::Overwrite => store
+                .change_password(&name, &|old| {
+                    let mut new = password.clone();
+                    // The import may not know about the previous passwords
+                    if new.history.is_empty() {
+                        new.history = old.history;
+                    }
+                    new
+                })
+                .map(|_| Some(String::from("overwritten"))),
+            Action::Rename => {
+                password.name = free_name(store, &name);
+                let what = format!("imported as {}", password.name);
+                store.add_password(password).map(|_| Some(what))
+            }
+        };
+
+        match result {
+            Ok(what) => {
+                if let Some(what) = what {
+                    io.info(outcome(&name, &what, dry_run), OutputType::Error);
+                }
+                if matches!(action, Action::Overwrite) {
+                    overwritten += 1;
+                } else {
+                    successes += 1;
+                }
+            }
+            Err(err) => {
+                io.error(format!("{}, error ({:?})", name, err), OutputType::Error);
+                problems.push(json!({
+                    "name": name,
+                    "level": "error",
+                    "code": err.code(),
+                }));
+                errors += 1;
+            }
         }
+    }
 
-        successes += 1;
+    if format == output::Format::Json {
+        let document = json!({
+            "imported": successes,
+            "overwritten": overwritten,
+            "warnings": warnings,
+            "errors": errors,
+            "problems": problems,
+            "dry_run": dry_run,
+        });
+        output::print_json(&document, io);
+        return Ok(());
     }
 
     io.success(format!("Imported: {}", successes), OutputType::Standard);
+    if overwritten > 0 {
+        io.success(
+            format!("Overwritten: {}", overwritten),
+            OutputType::Standard,
+        );
+    }
     io.warning(format!("Warnings: {}", warnings), OutputType::Error);
     io.error(format!("Errors: {}", errors), OutputType::Error);
+    // Listed last, so that they can be fixed in the file before importing again
+    for rejected in invalid.iter() {
+        io.error(format!("  {}", rejected), OutputType::Error);
+    }
+    if dry_run {
+        io.info(
+            "This was a dry run, the password store was not changed.",
+            OutputType::Standard,
+        );
+    }
 
     Ok(())
 }
 
+/// Tells why the export of another password manager couldn't be read
+fn read_export<T>(result: Result<T, String>, io: &mut impl CliInputOutput) -> Result<T, i32> {
+    result.map_err(|reason| {
+        io.error(
+            format!(
+                "Woops, I could not import the passwords (reason: {}).",
+                reason
+            ),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
 fn create_imported_passwords_from_csv(
     matches: &clap::ArgMatches,
     io: &mut impl CliInputOutput,
-) -> Result<(Vec<Password>, Vec<Password>), i32> {
+) -> Result<Imported, i32> {
     let path_str = matches.value_of("path").unwrap();
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(false)
+        // Files exported before details were added have fewer columns
+        .flexible(true)
         .from_path(path_str)
         .map_err(|err| {
             io.error(
@@ -92,30 +358,128 @@ fn create_imported_passwords_from_csv(
             );
             1
         })?;
-    let mut valid = vec![];
+    let mut imported = (vec![], vec![]);
     for record_result in reader.records() {
-        if let Ok(record) = record_result {
-            valid.push(Password {
-                name: record[0].into(),
-                username: record[1].into(),
-                password: record[2].into(),
-                created_at: ffi::time(),
-                updated_at: ffi::time(),
-            });
-        } else {
-            return Err(1);
+        let record = match read_export(csv_record(record_result, &mut imported), io)? {
+            Some(record) => record,
+            None => continue,
+        };
+        let line = csv_line(&record);
+        if record.len() < 3 {
+            let reason = "expected at least a name, a username and a password";
+            imported.1.push(Rejected::new(line, record.get(0), reason));
+            continue;
         }
+
+        // Fields are, in order: 0/Name, 1/Username, 2/Password, and since details were added
+        // 3/URL, 4/Notes, 5/Tags (comma separated), 6/Custom fields (JSON), 7/Created at,
+        // 8/Updated at (seconds since 1970)
+        let mut password = Password::new(&record[0], &record[1], &record[2]);
+        password.url = record
+            .get(3)
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_owned());
+        password.notes = record
+            .get(4)
+            .filter(|notes| !notes.is_empty())
+            .map(|notes| notes.to_owned());
+        password.tags = record.get(5).map(split_tags).unwrap_or_else(Vec::new);
+        if let Some(fields) = record.get(6).filter(|fields| !fields.is_empty()) {
+            match serde_json::from_str::<Vec<Field>>(fields) {
+                Ok(fields) => password.fields = fields,
+                Err(err) => {
+                    let reason = format!("invalid custom fields ({})", err);
+                    imported
+                        .1
+                        .push(Rejected::new(line, Some(&password.name), reason));
+                    continue;
+                }
+            }
+        }
+        match (parse_date(record.get(7)), parse_date(record.get(8))) {
+            (Ok(created_at), Ok(updated_at)) => {
+                set_timestamps(&mut password, created_at, updated_at)
+            }
+            (Err(reason), _) | (_, Err(reason)) => {
+                imported
+                    .1
+                    .push(Rejected::new(line, Some(&password.name), reason));
+                continue;
+            }
+        }
+        check_password(password, line, &mut imported);
+    }
+    Ok(imported)
+}
+
+/// Where the columns of a 1Password CSV file are
+struct OnePasswordColumns {
+    title: usize,
+    website: Option<usize>,
+    username: Option<usize>,
+    password: usize,
+    notes: Option<usize>,
+    kind: Option<usize>,
+    created_at: Option<usize>,
+    updated_at: Option<usize>,
+}
+
+impl OnePasswordColumns {
+    /// Older exports have no header row, and always these columns
+    const WITHOUT_HEADER: OnePasswordColumns = OnePasswordColumns {
+        notes: Some(0),
+        password: 1,
+        title: 2,
+        kind: Some(3),
+        website: Some(4),
+        username: Some(5),
+        created_at: None,
+        updated_at: None,
+    };
+
+    /// Finds the columns by name, if this record is a header row
+    fn from_header(record: &csv::StringRecord) -> Option<OnePasswordColumns> {
+        let find = |names: &[&str]| {
+            record
+                .iter()
+                .position(|header| names.iter().any(|n| header.trim().eq_ignore_ascii_case(n)))
+        };
+        Some(OnePasswordColumns {
+            title: find(&["Title"])?,
+            website: find(&["Website", "URL"]),
+            username: find(&["Username"]),
+            password: find(&["Password"])?,
+            notes: find(&["Notes"]),
+            kind: find(&["Type"]),
+            created_at: find(&["Created", "Created Date", "Created At"]),
+            updated_at: find(&["Modified", "Modified Date", "Updated", "Updated At"]),
+        })
+    }
+
+    fn count(&self) -> usize {
+        [
+            self.website,
+            self.username,
+            self.notes,
+            self.kind,
+            self.created_at,
+            self.updated_at,
+        ]
+        .iter()
+        .flatten()
+        .fold(self.title.max(self.password), |max, index| max.max(*index))
+            + 1
     }
-    return Ok((valid, vec![]));
 }
 
 fn create_imported_passwords_from_1password(
     matches: &clap::ArgMatches,
     io: &mut impl CliInputOutput,
-) -> Result<(Vec<Password>, Vec<Password>), i32> {
+) -> Result<Imported, i32> {
     let path_str = matches.value_of("path").unwrap();
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(false)
+        .flexible(true)
         .from_path(path_str)
         .map_err(|err| {
             io.error(
@@ -124,40 +488,76 @@ fn create_imported_passwords_from_1password(
             );
             1
         })?;
-    let mut valid = vec![];
-    let mut invalid = vec![];
+    let mut imported = (vec![], vec![]);
+    let mut columns = None;
     for record_result in reader.records() {
-        if let Ok(record) = record_result {
-            if &record[3] != "Login" {
-                invalid.push(Password {
-                    name: record[2].into(),
-                    username: record[5].into(),
-                    password: record[1].into(),
-                    created_at: ffi::time(),
-                    updated_at: ffi::time(),
-                });
-                continue;
+        let record = match read_export(csv_record(record_result, &mut imported), io)? {
+            Some(record) => record,
+            None => continue,
+        };
+        let columns = match columns {
+            Some(ref columns) => columns,
+            None => {
+                let header = OnePasswordColumns::from_header(&record);
+                let is_header = header.is_some();
+                let columns = columns.insert(header.unwrap_or(OnePasswordColumns::WITHOUT_HEADER));
+                if is_header {
+                    continue;
+                }
+                columns
             }
+        };
+        let line = csv_line(&record);
+        if record.len() < columns.count() {
+            let reason = format!("expected {} columns", columns.count());
+            imported
+                .1
+                .push(Rejected::new(line, record.get(columns.title), reason));
+            continue;
+        }
 
-            // Fields are, in order: 0/Notes, 1/Password, 2/Title, 3/Type (we can only import "Login"), 4/URL, 5/Username
-            valid.push(Password {
-                name: record[2].into(),
-                username: record[5].into(),
-                password: record[1].into(),
-                created_at: ffi::time(),
-                updated_at: ffi::time(),
-            });
-        } else {
-            return Err(1);
+        let get = |index: Option<usize>| index.and_then(|index| record.get(index)).unwrap_or("");
+        let mut password = Password::new(
+            &record[columns.title],
+            get(columns.username),
+            &record[columns.password],
+        );
+        password.url = Some(get(columns.website).to_owned()).filter(|url| !url.is_empty());
+        password.notes = Some(get(columns.notes).to_owned()).filter(|notes| !notes.is_empty());
+
+        // We can only import logins, exports with a single kind of item may have no type
+        let kind = get(columns.kind);
+        if !kind.is_empty() && kind != "Login" {
+            let reason = format!("only logins can be imported, not \"{}\"", kind);
+            imported
+                .1
+                .push(Rejected::new(line, Some(&password.name), reason));
+            continue;
         }
+
+        let created_at = parse_date(Some(get(columns.created_at)));
+        let updated_at = parse_date(Some(get(columns.updated_at)));
+        match (created_at, updated_at) {
+            (Ok(created_at), Ok(updated_at)) => {
+                set_timestamps(&mut password, created_at, updated_at)
+            }
+            (Err(reason), _) | (_, Err(reason)) => {
+                imported
+                    .1
+                    .push(Rejected::new(line, Some(&password.name), reason));
+                continue;
+            }
+        }
+
+        check_password(password, line, &mut imported);
     }
-    return Ok((valid, invalid));
+    Ok(imported)
 }
 
 fn create_imported_passwords_from_json(
     matches: &clap::ArgMatches,
     io: &mut impl CliInputOutput,
-) -> Result<(Vec<Password>, Vec<Password>), i32> {
+) -> Result<Imported, i32> {
     let path_str = matches.value_of("path").unwrap();
     let dump_file = File::open(path_str).map_err(|err| {
         io.error(
@@ -166,7 +566,7 @@ fn create_imported_passwords_from_json(
         );
         1
     })?;
-    let export: JsonExport = serde_json::from_reader(dump_file).map_err(|json_err| {
+    let export: JsonImport = serde_json::from_reader(dump_file).map_err(|json_err| {
         io.error(
             format!(
                 "Woops, I could not import the passwords from JSON (reason: {}).",
@@ -176,5 +576,66 @@ fn create_imported_passwords_from_json(
         );
         1
     })?;
-    Ok((export.passwords, vec![]))
+
+    let mut imported = (vec![], vec![]);
+    for (i, value) in export.passwords.into_iter().enumerate() {
+        let name = value["name"].as_str().map(|name| name.to_owned());
+        match serde_json::from_value::<Password>(value) {
+            Ok(password) => check_password(password, None, &mut imported),
+            Err(err) => {
+                let reason = format!("password {} is invalid ({})", i + 1, err);
+                imported
+                    .1
+                    .push(Rejected::new(None, name.as_deref(), reason));
+            }
+        }
+    }
+    Ok(imported)
+}
+
+fn create_imported_passwords_from_rooster_bundle(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+) -> Result<Imported, i32> {
+    let path_str = matches.value_of("path").unwrap();
+    let text = std::fs::read_to_string(path_str).map_err(|err| {
+        io.error(
+            format!("Uh oh, could not open or read the file (reason: {})", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+    let recipient_password = io
+        .prompt_password("Type the password of the bundle: ")
+        .map_err(|err| {
+            io.error(
+                format!(
+                    "I could not read the password of the bundle (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    let passwords = bundle::open(&text, recipient_password).map_err(|err| {
+        output::remember_error(&err);
+        let message = match err {
+            password::PasswordError::DecryptionError => {
+                String::from("Woops, that's not the password of this bundle.")
+            }
+            password::PasswordError::CorruptionError => {
+                String::from("Woops, this is not a Rooster bundle, or it was damaged.")
+            }
+            err => format!("Woops, I could not open the bundle (reason: {:?}).", err),
+        };
+        io.error(message, OutputType::Error);
+        1
+    })?;
+
+    let mut imported = (vec![], vec![]);
+    for password in passwords {
+        check_password(password, None, &mut imported);
+    }
+    Ok(imported)
 }
@@ -58,7 +58,7 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    let store = match crate::password::v2::PasswordStore::new(master_password) {
+    let store = match crate::password::v3::PasswordStore::new(master_password) {
         Ok(store) => store,
         Err(err) => {
             io.error(
@@ -73,33 +73,7 @@ pub fn callback_exec(
         }
     };
 
-    let mut file = match crate::create_password_file(filename_as_string.as_str()).map_err(|_| 1) {
-        Ok(file) => file,
-        Err(err) => {
-            io.error(
-                format!(
-                    "Woops, I couldn't create a new password file (reason: {:?})",
-                    err
-                ),
-                OutputType::Error,
-            );
-            return Err(1);
-        }
-    };
-
-    if let Err(err) = store.sync(&mut file) {
-        if let Err(err) = ::std::fs::remove_file(rooster_file_path) {
-            io.error(
-                format!(
-                    "Woops, I was able to create a new password file but couldn't save \
-                     it (reason: {:?}). You may want to remove this dangling file:",
-                    err
-                ),
-                OutputType::Error,
-            );
-            io.error(format!("    {}", filename_as_string), OutputType::Error);
-            return Err(1);
-        }
+    if let Err(err) = store.sync(rooster_file_path) {
         io.error(
             format!(
                 "Woops, I couldn't create a new password file (reason: {:?}).",
@@ -1,22 +1,56 @@
+use crate::ffi;
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use serde_json::json;
 
 pub fn callback_exec(
-    _matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
-    let passwords = store.get_all_passwords();
+    let passwords = list::Filter::from_matches(matches).apply(store, ffi::time());
 
-    if passwords.len() == 0 {
+    if output::Format::from_matches(matches) == output::Format::Json {
+        let passwords: Vec<_> = passwords
+            .iter()
+            .map(|p| {
+                json!({
+                    "name": p.name,
+                    "username": p.username,
+                    "url": p.url,
+                    "tags": p.tags,
+                    "created_at": p.created_at,
+                    "updated_at": p.updated_at,
+                })
+            })
+            .collect();
+        output::print_json(&json!({ "passwords": passwords }), io);
+        return Ok(());
+    }
+
+    if store.get_all_passwords().is_empty() {
         io.info(
             "No passwords on record yet. Add one with `rooster add <app> <username>`.",
             OutputType::Standard,
         );
+    } else if passwords.is_empty() {
+        io.info("No passwords match.", OutputType::Standard);
     } else {
-        list::print_list_of_passwords(&passwords, list::WITHOUT_NUMBERS, io);
+        let columns: Vec<list::Column> = matches
+            .values_of("column")
+            .map(|columns| {
+                columns
+                    .map(|column| match column {
+                        "created" => list::Column::Created,
+                        _ => list::Column::Updated,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        list::print_table_of_passwords(&passwords, &columns, io);
     }
 
     Ok(())
@@ -0,0 +1,37 @@
+#[cfg(unix)]
+use crate::agent;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+
+#[cfg(unix)]
+pub fn callback_exec(_matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    match agent::lock() {
+        Ok(true) => {
+            io.success(
+                "Done! The agent forgot your master password.",
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Ok(false) => {
+            io.info("The agent is not running.", OutputType::Standard);
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I could not lock the agent (reason: {}).", err),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn callback_exec(_matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    io.error(
+        "Woops, the agent is only available on Unix.",
+        OutputType::Error,
+    );
+    Err(1)
+}
@@ -1,14 +1,21 @@
 pub mod add;
+pub mod agent;
 pub mod change;
 pub mod delete;
 pub mod export;
 pub mod generate;
 pub mod get;
+pub mod history;
 pub mod import;
 pub mod init;
 pub mod list;
+pub mod lock;
+pub mod otp;
 pub mod regenerate;
 pub mod rename;
+pub mod restore_backup;
+pub mod rollback;
+pub mod set_kdf_params;
 pub mod set_master_password;
 pub mod set_scrypt_params;
 pub mod transfer;
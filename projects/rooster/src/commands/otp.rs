@@ -0,0 +1,68 @@
+use crate::clip;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let show = matches.is_present("show");
+    let query = matches.value_of("app").unwrap();
+
+    let prompt = format!(
+        "Which code would you like {}? ",
+        if show {
+            "to see"
+        } else {
+            "to copy to your clipboard"
+        },
+    );
+    let password =
+        list::search_and_pick_password(store, query, list::WITH_NUMBERS, &prompt, io).ok_or(1)?;
+
+    let otp = match &password.otp {
+        Some(otp) => otp,
+        None => {
+            io.error(
+                format!(
+                    "Woops, there is no two factor authentication secret for {}. Add one with \
+                     `rooster change '{}' --otp <uri or secret>`.",
+                    password.name, password.name
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    let now = ffi::time() as u64;
+    let code = otp.code(now).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't compute the code (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    clip::confirm_field_retrieved(
+        show,
+        password,
+        "one-time code",
+        &code,
+        &clip::ClipboardOptions::from_matches(matches),
+        io,
+    );
+    io.info(
+        format!(
+            "It is valid for {} more seconds.",
+            otp.seconds_remaining(now)
+        ),
+        OutputType::Standard,
+    );
+
+    Ok(())
+}
@@ -1,14 +1,15 @@
 use crate::clip;
 use crate::ffi;
-use crate::generate::{check_password_len, PasswordSpec};
+use crate::generate::{report_entropy, PasswordSpec};
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let query = matches.value_of("app").unwrap();
@@ -23,12 +24,7 @@ pub fn callback_exec(
     .ok_or(1)?
     .clone();
 
-    let pwspec = PasswordSpec::new(
-        matches.is_present("alnum"),
-        matches
-            .value_of("length")
-            .and_then(|len| check_password_len(len.parse::<usize>().ok(), io)),
-    );
+    let pwspec = PasswordSpec::from_matches(matches, password.policy.as_ref(), io)?;
 
     let password_as_string = match pwspec.generate_hard_password() {
         Ok(password_as_string) => password_as_string,
@@ -44,24 +40,37 @@ pub fn callback_exec(
         }
     };
 
+    // A new policy replaces the one used so far.
+    let policy = if matches.is_present("policy") {
+        Some(pwspec.policy.clone())
+    } else {
+        password.policy.clone()
+    };
+
     let change_result =
-        store.change_password(&password.name, &|old_password: password::v2::Password| {
-            password::v2::Password {
-                name: old_password.name.clone(),
-                username: old_password.username.clone(),
+        store.change_password(&password.name, &|old_password: password::v3::Password| {
+            password::v3::Password {
                 password: password_as_string.clone(),
-                created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                policy: policy.clone(),
+                ..old_password
             }
         });
 
     match change_result {
         Ok(password) => {
+            report_entropy(&pwspec, io);
             let show = matches.is_present("show");
-            clip::confirm_password_retrieved(show, &password, io);
+            clip::confirm_password_retrieved(
+                show,
+                &password,
+                &clip::ClipboardOptions::from_matches(matches),
+                io,
+            );
             Ok(())
         }
         Err(err) => {
+            output::remember_error(&err);
             io.error(
                 format!(
                     "Woops, I couldn't save the new password (reason: {:?}).",
@@ -1,12 +1,13 @@
 use crate::ffi;
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let query = matches.value_of("app").unwrap();
@@ -23,13 +24,11 @@ pub fn callback_exec(
     .clone();
 
     let change_result =
-        store.change_password(&password.name, &|old_password: password::v2::Password| {
-            password::v2::Password {
+        store.change_password(&password.name, &|old_password: password::v3::Password| {
+            password::v3::Password {
                 name: new_name.clone(),
-                username: old_password.username.clone(),
-                password: old_password.password.clone(),
-                created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                ..old_password
             }
         });
 
@@ -42,6 +41,7 @@ pub fn callback_exec(
             Ok(())
         }
         Err(err) => {
+            output::remember_error(&err);
             io.error(
                 format!(
                     "Woops, I couldn't save the new app name (reason: {:?}).",
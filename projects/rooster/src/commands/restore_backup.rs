@@ -0,0 +1,102 @@
+use crate::list::format_age;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::safe_file;
+use std::path::Path;
+use std::time::SystemTime;
+
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let backups = safe_file::list_backups(rooster_file_path).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't list the backups (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    if backups.is_empty() {
+        io.error(
+            "There are no backups of your password file yet.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    let now = SystemTime::now();
+    for backup in backups.iter() {
+        io.info(
+            format!(
+                "{} {} ({})",
+                backup.number,
+                backup.path.to_string_lossy(),
+                format_age(backup.modified, now)
+            ),
+            OutputType::Standard,
+        );
+    }
+    io.nl(OutputType::Standard);
+
+    let backup = loop {
+        io.write(
+            format!(
+                "Which backup would you like to restore? Type a number from 1 to {}: ",
+                backups.len()
+            ),
+            OutputType::Standard,
+        );
+
+        let line = io.read_line().map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't read your answer (reason: {}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+        match line.trim().parse::<usize>() {
+            Ok(number) if number >= 1 && number <= backups.len() => {
+                break &backups[number - 1];
+            }
+            _ => {
+                io.error(
+                    format!("I need a number between 1 and {}.", backups.len()),
+                    OutputType::Error,
+                );
+            }
+        }
+    };
+
+    let restore = || -> std::io::Result<()> {
+        // Read the backup before rotating, as rotating moves it around.
+        let contents = std::fs::read(&backup.path)?;
+
+        // The current password file becomes a backup too, in case restoring was a mistake.
+        let backup_count =
+            safe_file::parse_backup_count(std::env::var("ROOSTER_BACKUPS").ok().as_deref())?;
+        safe_file::rotate_backups(rooster_file_path, backup_count)?;
+
+        safe_file::write_atomically(rooster_file_path, &contents)
+    };
+
+    if let Err(err) = restore() {
+        io.error(
+            format!("Woops, I couldn't restore the backup (reason: {}).", err),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    io.success(
+        format!(
+            "Done! I've restored your password file from backup {}.",
+            backup.number
+        ),
+        OutputType::Standard,
+    );
+
+    Ok(())
+}
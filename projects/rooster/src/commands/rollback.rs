@@ -0,0 +1,72 @@
+use crate::clip;
+use crate::commands::history::choose_version;
+use crate::ffi;
+use crate::list;
+use crate::output;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.value_of("app").unwrap();
+    let n = matches.value_of("n").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::WITH_NUMBERS,
+        "Which password would you like to roll back?",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    if password.history.is_empty() {
+        io.error(
+            format!(
+                "Woops, there are no previous passwords for {}.",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    let previous = choose_version(&password, n, io)?.clone();
+
+    // The current password goes to the history, so rolling back can be undone too.
+    let password = store
+        .change_password(&password.name, &|old_password: password::v3::Password| {
+            let mut new_password = password::v3::Password {
+                password: previous.password.clone(),
+                updated_at: ffi::time(),
+                ..old_password
+            };
+            new_password.history.retain(|p| p != &previous);
+            new_password
+        })
+        .map_err(|err| {
+            output::remember_error(&err);
+            io.error(
+                format!(
+                    "Woops, I couldn't restore the previous password (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    let show = matches.is_present("show");
+    clip::confirm_password_retrieved(
+        show,
+        &password,
+        &clip::ClipboardOptions::from_matches(matches),
+        io,
+    );
+    Ok(())
+}
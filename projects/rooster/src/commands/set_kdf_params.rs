@@ -0,0 +1,100 @@
+use crate::password;
+use crate::password::v3::Kdf;
+use crate::rclio::{CliInputOutput, OutputType};
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    match matches.subcommand() {
+        Some(("argon2id", subcommand_matches)) => {
+            set_argon2id_params(subcommand_matches, store, io)
+        }
+        Some((name, _)) => {
+            io.error(
+                format!(
+                    "Woops, I don't know the \"{}\" key derivation function.",
+                    name
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+        None => {
+            io.error(
+                "Woops, you need to pick a key derivation function.",
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+fn set_argon2id_params(
+    matches: &clap::ArgMatches,
+    store: &mut password::v3::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let parse = |name: &str| -> Result<u32, String> {
+        matches
+            .value_of(name)
+            .unwrap()
+            .trim()
+            .parse::<u32>()
+            .map_err(|err| format!("The {} parameter is invalid ({})", name, err))
+    };
+    let (memory, iterations, parallelism) =
+        match (parse("memory"), parse("iterations"), parse("parallelism")) {
+            (Ok(memory), Ok(iterations), Ok(parallelism)) => (memory, iterations, parallelism),
+            (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+                io.error(err, OutputType::Error);
+                return Err(1);
+            }
+        };
+
+    if memory == 0 || iterations == 0 || parallelism == 0 {
+        io.error(
+            format!(
+                "The parameters must be > 0 ({}, {}, {})",
+                memory, iterations, parallelism
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    // Even with --force, a file with higher parameters would be refused as corrupted
+    let kdf = Kdf::Argon2id {
+        memory,
+        iterations,
+        parallelism,
+    };
+    if let Err(err) = kdf.check() {
+        io.error(
+            format!("These parameters are not valid for argon2id ({}).", err),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    if !matches.is_present("force") && (memory > 1024 * 1024 || iterations > 10 || parallelism > 8)
+    {
+        io.error("These parameters seem very high. You might be unable to open your password file ever again. Aborting.", OutputType::Error);
+        io.error(
+            "Run with --force to force, but make a backup of your password file first.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    store
+        .change_argon2id_params(memory, iterations, parallelism)
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I could not derive the new key (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })
+}
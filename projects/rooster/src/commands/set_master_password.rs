@@ -5,7 +5,7 @@ use std::ops::Deref;
 
 pub fn callback_exec(
     _matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     match io.prompt_password("Type your new master password: ") {
@@ -33,7 +33,13 @@ pub fn callback_exec(
                 return Err(1);
             }
 
-            store.change_master_password(master_password.deref());
+            if let Err(err) = store.change_master_password(master_password.deref()) {
+                io.error(
+                    format!("Woops, I could not derive the new key (reason: {:?}).", err),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
         }
         Err(err) => {
             io.error(
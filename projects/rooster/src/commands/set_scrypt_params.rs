@@ -1,9 +1,10 @@
 use crate::password;
+use crate::password::v3::Kdf;
 use crate::rclio::{CliInputOutput, OutputType};
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let log2_n = matches
@@ -33,6 +34,15 @@ pub fn callback_exec(
         return Err(1);
     }
 
+    // Even with --force, a file with higher parameters would be refused as corrupted
+    if let Err(err) = (Kdf::Scrypt { log2_n, r, p }).check() {
+        io.error(
+            format!("These parameters are not valid for scrypt ({}).", err),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
     if !matches.is_present("force") && (log2_n > 20 || r > 8 || p > 1) {
         io.error("These parameters seem very high. You might be unable to open your password file ever again. Aborting.", OutputType::Error);
         io.error(
@@ -42,7 +52,11 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    store.change_scrypt_params(log2_n, r, p);
-
-    Ok(())
+    store.change_scrypt_params(log2_n, r, p).map_err(|err| {
+        io.error(
+            format!("Woops, I could not derive the new key (reason: {:?}).", err),
+            OutputType::Error,
+        );
+        1
+    })
 }
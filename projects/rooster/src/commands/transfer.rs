@@ -1,12 +1,13 @@
 use crate::ffi;
 use crate::list;
+use crate::output;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
-    store: &mut password::v2::PasswordStore,
+    store: &mut password::v3::PasswordStore,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let query = matches.value_of("app").unwrap();
@@ -25,13 +26,11 @@ pub fn callback_exec(
     let old_username = password.username;
 
     let change_result =
-        store.change_password(&password.name, &|old_password: password::v2::Password| {
-            password::v2::Password {
-                name: old_password.name.clone(),
+        store.change_password(&password.name, &|old_password: password::v3::Password| {
+            password::v3::Password {
                 username: new_username.clone(),
-                password: old_password.password.clone(),
-                created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                ..old_password
             }
         });
 
@@ -44,6 +43,7 @@ pub fn callback_exec(
             Ok(())
         }
         Err(err) => {
+            output::remember_error(&err);
             io.error(
                 format!(
                     "Woops, I couldn't save the new app name (reason: {:?}).",
@@ -0,0 +1,91 @@
+use crate::password::v3::{Field, Password};
+use crate::totp::Otp;
+
+/// Names of the arguments that set the details of a password (everything but the password itself)
+pub const DETAILS_ARGS: [&str; 6] = ["url", "notes", "tag", "field", "secret-field", "otp"];
+
+/// Checks the format of a `--field` value, ie: `PIN=1234`
+pub fn validate_field(v: &str) -> Result<(), String> {
+    match v.split_once('=') {
+        Some((name, _)) if !name.trim().is_empty() => Ok(()),
+        _ => Err(String::from("The value must look like name=value")),
+    }
+}
+
+/// Checks the format of an `--otp` value, an `otpauth://` URI or a base32 secret
+pub fn validate_otp(v: &str) -> Result<(), String> {
+    if v.is_empty() {
+        return Ok(());
+    }
+    Otp::parse(v).map(|_| ())
+}
+
+fn parse_field(v: &str, secret: bool) -> Field {
+    // Validation is done by `clap` before, see `validate_field`.
+    let (name, value) = v.split_once('=').unwrap();
+    Field {
+        name: name.trim().to_owned(),
+        value: value.to_owned().into(),
+        secret,
+    }
+}
+
+/// Whether any of the details arguments was given on the command line
+pub fn has_details(matches: &clap::ArgMatches) -> bool {
+    DETAILS_ARGS.iter().any(|arg| matches.is_present(arg))
+}
+
+/// Updates a password with the details given on the command line
+///
+/// An empty `--url`, `--notes` or `--otp` removes it. `--tag` replaces all the existing tags.
+pub fn apply_details(matches: &clap::ArgMatches, password: &mut Password) {
+    if let Some(url) = matches.value_of("url") {
+        password.url = Some(url.to_owned()).filter(|url| !url.is_empty());
+    }
+
+    if let Some(notes) = matches.value_of("notes") {
+        password.notes = Some(notes.to_owned()).filter(|notes| !notes.is_empty());
+    }
+
+    if let Some(tags) = matches.values_of("tag") {
+        password.tags = tags
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| tag.to_owned())
+            .collect();
+    }
+
+    if let Some(fields) = matches.values_of("field") {
+        for field in fields {
+            password.set_field(parse_field(field, false));
+        }
+    }
+
+    if let Some(fields) = matches.values_of("secret-field") {
+        for field in fields {
+            password.set_field(parse_field(field, true));
+        }
+    }
+
+    if let Some(otp) = matches.value_of("otp") {
+        // Validation is done by `clap` before, see `validate_otp`.
+        password.otp = Otp::parse(otp).ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_field, validate_field};
+
+    #[test]
+    fn test_parse_field() {
+        assert!(validate_field("PIN=1234").is_ok());
+        assert!(validate_field("Question=What is 1+1? =2").is_ok());
+        assert!(validate_field("PIN").is_err());
+        assert!(validate_field(" =1234").is_err());
+
+        let field = parse_field("Question=What is 1+1? =2", true);
+        assert_eq!(field.name, "Question");
+        assert_eq!(field.value, "What is 1+1? =2".into());
+        assert!(field.secret);
+    }
+}
@@ -0,0 +1,177 @@
+//! Fuzzy matching of app names, in the spirit of fzf and skim
+//!
+//! The characters of the query must be found in the app name, in the same order. Among all the
+//! ways to place them, the best one gets the score, so that "gh" ranks "GitHub" above
+//! "Google Home".
+
+/// Points for every character of the query found in the app name
+const SCORE_MATCH: i32 = 16;
+/// The character starts the app name, ie: "g" in "GitHub"
+const BONUS_PREFIX: i32 = 8;
+/// The character starts a word, ie: "h" in "GitHub" or in "Google Home"
+const BONUS_BOUNDARY: i32 = 8;
+/// The character follows the one matched before, ie: "i" after "g" in "GitHub"
+const BONUS_CONSECUTIVE: i32 = 8;
+/// Skipping characters between two matched characters
+const PENALTY_GAP_START: i32 = 3;
+const PENALTY_GAP_EXTENSION: i32 = 1;
+/// Characters before the first match, up to a limit so that long names aren't punished too much
+const PENALTY_LEADING: i32 = 1;
+const MAX_PENALTY_LEADING: i32 = 8;
+
+/// A match is strong if it scores at least this much for each character of the query, which
+/// takes the query to be found at word starts or in one piece.
+const STRONG_MATCH_PER_CHAR: i32 = SCORE_MATCH + BONUS_CONSECUTIVE;
+
+/// How much better than the runner-up the best match must be to be picked right away, in percent
+const CLEAR_WINNER_PERCENT: i32 = 150;
+
+/// Whether the character at `i` starts a word
+fn is_boundary(chars: &[char], i: usize) -> bool {
+    if i == 0 {
+        return true;
+    }
+    let (previous, current) = (chars[i - 1], chars[i]);
+    // "Google Home", "my-bank", "me@example.com"
+    !previous.is_alphanumeric() && current.is_alphanumeric()
+        // "GitHub", "PayPal"
+        || previous.is_lowercase() && current.is_uppercase()
+        // "Route66"
+        || previous.is_alphabetic() && current.is_numeric()
+}
+
+fn gap_penalty(gap: usize) -> i32 {
+    if gap == 0 {
+        return 0;
+    }
+    PENALTY_GAP_START + PENALTY_GAP_EXTENSION * (gap as i32 - 1)
+}
+
+/// Scores how well the query matches the name, or `None` if it doesn't match at all
+///
+/// The comparison ignores case. An empty query matches everything with a score of 0.
+pub fn score(query: &str, name: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().flat_map(|c| c.to_lowercase()).collect();
+    let chars: Vec<char> = name.chars().collect();
+    let lowercase: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    // best[j]: the best score with the current query character matched at position j of the
+    // name, given that all the previous ones were matched before j.
+    let mut best: Vec<Option<i32>> = vec![None; chars.len()];
+
+    for (i, q) in query.iter().enumerate() {
+        let mut next: Vec<Option<i32>> = vec![None; chars.len()];
+
+        for j in 0..chars.len() {
+            if lowercase[j] != *q {
+                continue;
+            }
+
+            let mut bonus = SCORE_MATCH;
+            if j == 0 {
+                bonus += BONUS_PREFIX;
+            }
+            if is_boundary(&chars, j) {
+                bonus += BONUS_BOUNDARY;
+            }
+
+            next[j] = if i == 0 {
+                Some(bonus - (PENALTY_LEADING * j as i32).min(MAX_PENALTY_LEADING))
+            } else {
+                (0..j)
+                    .filter_map(|k| {
+                        let previous = best[k]?;
+                        Some(if k + 1 == j {
+                            previous + BONUS_CONSECUTIVE
+                        } else {
+                            previous - gap_penalty(j - k - 1)
+                        })
+                    })
+                    .max()
+                    .map(|previous| previous + bonus)
+            };
+        }
+
+        best = next;
+    }
+
+    best.into_iter().flatten().max()
+}
+
+/// Whether the best of some scores, sorted from best to worst, stands out enough to be picked
+/// without asking
+pub fn is_clear_winner(query: &str, scores: &[i32]) -> bool {
+    let best = match scores.first() {
+        Some(best) => *best,
+        None => return false,
+    };
+    if best < STRONG_MATCH_PER_CHAR * query.chars().count() as i32 {
+        return false;
+    }
+    match scores.get(1) {
+        Some(runner_up) => best * 100 >= CLEAR_WINNER_PERCENT * runner_up.max(&1),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_clear_winner, score};
+
+    #[test]
+    fn test_score_needs_all_chars_in_order() {
+        assert!(score("gh", "GitHub").is_some());
+        assert!(score("GH", "github").is_some());
+        assert!(score("fcbk", "Facebook").is_some());
+        assert!(score("hg", "GitHub").is_none());
+        assert!(score("gx", "GitHub").is_none());
+        assert!(score("github2", "GitHub").is_none());
+        assert_eq!(score("", "GitHub"), Some(0));
+    }
+
+    #[test]
+    fn test_score_ranks_word_starts_and_contiguous_matches_first() {
+        let rank =
+            |query: &str, a: &str, b: &str| score(query, a).unwrap() > score(query, b).unwrap();
+
+        assert!(rank("gh", "GitHub", "Google Home"));
+        assert!(rank("gh", "Google Home", "Bigshot"));
+        assert!(rank("git", "GitHub", "Digital"));
+        assert!(rank("mail", "Mail", "My Email"));
+        assert!(rank("bank", "my-bank", "bbaannkk"));
+        assert!(rank("tube", "YouTube", "The Ultimate Bank Exchange"));
+    }
+
+    #[test]
+    fn test_is_clear_winner() {
+        let scores = |query: &str, names: &[&str]| {
+            let mut scores: Vec<i32> = names.iter().filter_map(|n| score(query, n)).collect();
+            scores.sort_by(|a, b| b.cmp(a));
+            scores
+        };
+
+        assert!(is_clear_winner("git", &scores("git", &["GitHub"])));
+        assert!(is_clear_winner(
+            "gith",
+            &scores("gith", &["GitHub", "Google Home"])
+        ));
+        assert!(is_clear_winner(
+            "mail",
+            &scores("mail", &["Mail", "Hamburger Railway"])
+        ));
+        assert!(!is_clear_winner(
+            "gh",
+            &scores("gh", &["GitHub", "Google Home"])
+        ));
+        // A single match that is all over the place is not trusted
+        assert!(!is_clear_winner("ab", &scores("ab", &["Amazon Web"])));
+        assert!(!is_clear_winner("x", &[]));
+    }
+}
@@ -2,59 +2,315 @@ use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 use crate::rutil::safe_string::SafeString;
 use rand::{rngs::OsRng, Rng};
+use serde::{Deserialize, Serialize};
 use std::io::Result as IoResult;
+use std::ops::Deref;
 
-fn generate_password(alnum: bool, len: usize) -> IoResult<SafeString> {
-    let mut password_as_string = String::new();
-    let mut rng = OsRng::default();
-    for _ in 0..len {
-        if alnum {
-            match rng.gen_range(0..3) {
-                // Numbers 0-9
-                0 => password_as_string.push(rng.gen_range(48..58) as u8 as char),
-                // Uppercase A-Z
-                1 => password_as_string.push(rng.gen_range(65..91) as u8 as char),
-                // Lowercase a-z
-                2 => password_as_string.push(rng.gen_range(97..123) as u8 as char),
-                _ => unreachable!(),
-            }
-        } else {
-            password_as_string.push(rng.gen_range(33..127) as u8 as char);
+/// Words for passphrases, one per line with its dice roll, ie: `11111\tabandon`
+///
+/// There are 6^5 = 7776 words of 4 to 9 letters, so that a word can be picked with five dice.
+/// This is not the EFF large wordlist, but it has the same size and format, so the EFF file can
+/// be dropped in as is. The entropy is computed from the size of the list either way.
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+fn wordlist() -> Vec<&'static str> {
+    WORDLIST
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .collect()
+}
+
+fn generate_passphrase(spec: &PassphraseSpec) -> IoResult<SafeString> {
+    let words = wordlist();
+    let mut rng = OsRng;
+
+    // SafeString, so that the words are zeroed once the passphrase is built.
+    let mut chosen: Vec<SafeString> = (0..spec.words)
+        .map(|_| SafeString::from_string(words[rng.gen_range(0..words.len())].to_owned()))
+        .collect();
+
+    if spec.capitalize {
+        for word in chosen.iter_mut() {
+            word[..1].make_ascii_uppercase();
         }
     }
-    Ok(SafeString::from_string(password_as_string))
+
+    if spec.digit {
+        let i = rng.gen_range(0..chosen.len());
+        chosen[i].push(rng.gen_range(b'0'..=b'9') as char);
+    }
+
+    let passphrase = SafeString::from_string(
+        chosen
+            .iter()
+            .map(|word| word.deref().as_str())
+            .collect::<Vec<&str>>()
+            .join(&spec.separator),
+    );
+    Ok(passphrase)
 }
 
-/// Returns true if the password contains at least one digit, one uppercase letter and one
-/// lowercase letter.
-fn password_is_hard(password: &str, alnum: bool) -> bool {
-    let is_punctuation = |c| -> bool { "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~".find(c).is_some() };
+/// Characters that are easy to mix up when reading or typing a password
+const AMBIGUOUS: &str = "0O1lI|";
+
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Rules for the characters of generated passwords, for websites with odd requirements
+///
+/// A policy is written as space separated rules, ie: `length=16 symbol=2 symbols=@#$ exclude=0`
+/// - `length=N`: the length of the password
+/// - `lower=N`, `upper=N`, `digit=N`, `symbol=N`: use at least N characters of the class, or
+///   none at all with `no` instead of a number
+/// - `symbols=CHARS`: the characters of the symbol class
+/// - `include=CHARS`: other characters that can be used
+/// - `exclude=CHARS`: characters that must never be used
+/// - `no-ambiguous`: avoid characters that are easy to mix up, like 0/O and l/1
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Policy {
+    pub length: usize,
+    /// Minimum number of characters of each class, `None` when the class isn't used at all
+    pub lower: Option<usize>,
+    pub upper: Option<usize>,
+    pub digit: Option<usize>,
+    pub symbol: Option<usize>,
+    pub symbols: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub include: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub exclude: String,
+    #[serde(default)]
+    pub avoid_ambiguous: bool,
+}
+
+impl Policy {
+    /// The policy used when none is given: at least a digit, a lowercase and an uppercase letter
+    /// and a symbol, unless `alnum` is set.
+    pub fn new(alnum: bool, length: usize) -> Policy {
+        Policy {
+            length,
+            lower: Some(1),
+            upper: Some(1),
+            digit: Some(1),
+            symbol: if alnum { None } else { Some(1) },
+            symbols: SYMBOLS.to_owned(),
+            include: String::new(),
+            exclude: String::new(),
+            avoid_ambiguous: false,
+        }
+    }
+
+    /// Applies the rules of a policy, see `Policy` for the format
+    pub fn apply(&mut self, rules: &str) -> Result<(), String> {
+        for rule in rules.split_whitespace() {
+            if rule == "no-ambiguous" {
+                self.avoid_ambiguous = true;
+                continue;
+            }
+
+            let (name, value) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("\"{}\" should look like name=value", rule))?;
+            let count = || -> Result<Option<usize>, String> {
+                match value {
+                    "no" => Ok(None),
+                    _ => value
+                        .parse::<usize>()
+                        .map(Some)
+                        .map_err(|_| format!("\"{}\" should be a number or \"no\"", rule)),
+                }
+            };
+
+            match name {
+                "length" => {
+                    self.length = value
+                        .parse::<usize>()
+                        .map_err(|_| format!("\"{}\" should be a number", rule))?
+                }
+                "lower" => self.lower = count()?,
+                "upper" => self.upper = count()?,
+                "digit" => self.digit = count()?,
+                "symbol" => self.symbol = count()?,
+                "symbols" => self.symbols = value.to_owned(),
+                "include" => self.include = value.to_owned(),
+                "exclude" => self.exclude = value.to_owned(),
+                _ => return Err(format!("\"{}\" is not a rule I know", name)),
+            }
+        }
+
+        self.check()
+    }
+
+    /// The characters of each class that is used, along with the minimum count for the class
+    fn classes(&self) -> Vec<(Vec<char>, usize)> {
+        let forbidden =
+            |c: char| self.exclude.contains(c) || (self.avoid_ambiguous && AMBIGUOUS.contains(c));
+        let allowed = |c: &char| !forbidden(*c);
+
+        let mut classes = vec![];
+        for (chars, min) in &[
+            (LOWER, self.lower),
+            (UPPER, self.upper),
+            (DIGITS, self.digit),
+            (self.symbols.as_str(), self.symbol),
+        ] {
+            if let Some(min) = min {
+                classes.push((chars.chars().filter(allowed).collect(), *min));
+            }
+        }
+        classes.push((self.include.chars().filter(allowed).collect(), 0));
+        classes
+    }
+
+    /// All the characters a password can be made of
+    fn alphabet(&self) -> Vec<char> {
+        let mut alphabet: Vec<char> = self
+            .classes()
+            .into_iter()
+            .flat_map(|(chars, _)| chars)
+            .collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        alphabet
+    }
+
+    /// Makes sure passwords can be generated with this policy
+    pub fn check(&self) -> Result<(), String> {
+        if self.length == 0 {
+            return Err(String::from("The length must be > 0"));
+        }
+        if self.alphabet().is_empty() {
+            return Err(String::from("There are no characters left to use"));
+        }
+        for (chars, min) in self.classes() {
+            if min > 0 && chars.is_empty() {
+                return Err(String::from(
+                    "A class needs characters, but they are all excluded",
+                ));
+            }
+        }
+        let min_length: usize = self.classes().iter().map(|(_, min)| min).sum();
+        if min_length > self.length {
+            return Err(format!(
+                "The length must be at least {} to fit the minimum counts",
+                min_length
+            ));
+        }
+        Ok(())
+    }
+
+    fn generate(&self) -> IoResult<SafeString> {
+        let mut rng = OsRng;
+        let alphabet = self.alphabet();
 
-    password.find(char::is_numeric).is_some()
-        && password.find(char::is_lowercase).is_some()
-        && password.find(char::is_uppercase).is_some()
-        && (alnum || password.find(is_punctuation).is_some())
+        // First the characters required by each class, then anything from the alphabet.
+        let mut password: Vec<char> = vec![];
+        for (chars, min) in self.classes() {
+            for _ in 0..min {
+                password.push(chars[rng.gen_range(0..chars.len())]);
+            }
+        }
+        while password.len() < self.length {
+            password.push(alphabet[rng.gen_range(0..alphabet.len())]);
+        }
+
+        // Shuffle, so the required characters can be anywhere.
+        for i in (1..password.len()).rev() {
+            password.swap(i, rng.gen_range(0..=i));
+        }
+
+        let password_as_string = SafeString::from_string(password.iter().collect());
+        for c in password.iter_mut() {
+            *c = '\0';
+        }
+        Ok(password_as_string)
+    }
+}
+
+/// Settings for passphrases made of random words, ie: `Zoom-abrupt-ability7-Zones`
+pub struct PassphraseSpec {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    pub digit: bool,
 }
 
 pub struct PasswordSpec {
-    pub alnum: bool,
-    pub len: usize,
+    pub policy: Policy,
+    /// When set, a passphrase is generated instead of random characters
+    pub passphrase: Option<PassphraseSpec>,
 }
 
 impl PasswordSpec {
     pub fn new(alnum: bool, password_len: Option<usize>) -> PasswordSpec {
         PasswordSpec {
-            alnum: alnum,
-            len: password_len.unwrap_or(32),
+            policy: Policy::new(alnum, password_len.unwrap_or(32)),
+            passphrase: None,
         }
     }
 
-    pub fn generate_hard_password(&self) -> IoResult<SafeString> {
-        loop {
-            let password = generate_password(self.alnum, self.len)?;
-            if password_is_hard(password.as_ref(), self.alnum) {
-                return Ok(password);
+    /// Reads the spec from the options of `generate` and `regenerate`
+    ///
+    /// The policy stored for the password is used, unless other options are given.
+    pub fn from_matches(
+        matches: &clap::ArgMatches,
+        stored_policy: Option<&Policy>,
+        io: &mut impl CliInputOutput,
+    ) -> Result<PasswordSpec, i32> {
+        let mut spec = PasswordSpec::new(
+            matches.is_present("alnum"),
+            matches
+                .value_of("length")
+                .and_then(|len| check_password_len(len.parse::<usize>().ok(), io)),
+        );
+
+        if let Some(rules) = matches.value_of("policy") {
+            if let Err(err) = spec.policy.apply(rules) {
+                io.error(
+                    format!("Woops! I can't use this policy: {}.", err),
+                    OutputType::Error,
+                );
+                return Err(1);
             }
+        } else if let Some(stored_policy) = stored_policy {
+            if matches.occurrences_of("alnum") == 0 && matches.occurrences_of("length") == 0 {
+                spec.policy = stored_policy.clone();
+            }
+        }
+
+        if let Some(words) = matches.value_of("words") {
+            spec.passphrase = Some(PassphraseSpec {
+                words: check_word_count(words.parse::<usize>().ok(), io).ok_or(1)?,
+                separator: matches.value_of("separator").unwrap_or("-").to_owned(),
+                capitalize: matches.is_present("capitalize"),
+                digit: matches.is_present("digit"),
+            });
+        }
+
+        Ok(spec)
+    }
+
+    /// Number of bits of entropy of the generated passwords
+    pub fn entropy(&self) -> f64 {
+        match &self.passphrase {
+            Some(passphrase) => {
+                let mut bits = passphrase.words as f64 * (wordlist().len() as f64).log2();
+                if passphrase.digit {
+                    // Which digit, and which word it goes after
+                    bits += (10.0 * passphrase.words as f64).log2();
+                }
+                bits
+            }
+            None => self.policy.length as f64 * (self.policy.alphabet().len() as f64).log2(),
+        }
+    }
+
+    pub fn generate_hard_password(&self) -> IoResult<SafeString> {
+        match &self.passphrase {
+            Some(passphrase) => generate_passphrase(passphrase),
+            None => self.policy.generate(),
         }
     }
 }
@@ -63,10 +319,8 @@ pub fn check_password_len(opt: Option<usize>, io: &mut impl CliInputOutput) -> O
     match opt {
         Some(len) => {
             // We want passwords to contain at least one uppercase letter, one lowercase
-            // letter and one digit. So we need at least 4 characters for each password.
-            // This checks makes sure we don't run into an infinite loop trying to generate
-            // a password of length < 4 with 4 different kinds of characters (uppercase,
-            // lowercase, numeric, punctuation).
+            // letter, one digit and one symbol. So we need at least 4 characters for each
+            // password. Policies can go lower, see `Policy::check`.
             if len < 4 {
                 io.error("Woops! The length of the password must be at least 4. This allows us to make sure your password is secure.", OutputType::Error);
                 None
@@ -84,11 +338,102 @@ pub fn check_password_len(opt: Option<usize>, io: &mut impl CliInputOutput) -> O
     }
 }
 
+pub fn report_entropy(spec: &PasswordSpec, io: &mut impl CliInputOutput) {
+    io.info(
+        format!(
+            "This password has about {:.0} bits of entropy.",
+            spec.entropy()
+        ),
+        OutputType::Standard,
+    );
+}
+
+pub fn check_word_count(opt: Option<usize>, io: &mut impl CliInputOutput) -> Option<usize> {
+    match opt {
+        // Fewer words are too easy to guess, even with a digit.
+        Some(words) if words < 3 => {
+            io.error(
+                "Woops! A passphrase needs at least 3 words to be secure.",
+                OutputType::Error,
+            );
+            None
+        }
+        Some(words) => Some(words),
+        None => {
+            io.error(
+                "Woops! The words option must be a valid number, for instance 6.",
+                OutputType::Error,
+            );
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use crate::generate::PasswordSpec;
+    use crate::generate::{wordlist, PassphraseSpec, PasswordSpec, Policy, WORDLIST};
     use std::ops::Deref;
 
+    #[test]
+    fn test_wordlist() {
+        let words = wordlist();
+        assert_eq!(words.len(), 7776);
+
+        let mut unique = words.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 7776);
+
+        // One word for each roll of five dice, in order, like the EFF large wordlist
+        let rolls: Vec<&str> = WORDLIST
+            .lines()
+            .map(|line| line.split('\t').next().unwrap())
+            .collect();
+        let mut expected = Vec::new();
+        for n in 0..7776 {
+            let roll: String = (0..5)
+                .rev()
+                .map(|i| char::from(b'1' + (n / 6usize.pow(i) % 6) as u8))
+                .collect();
+            expected.push(roll);
+        }
+        assert_eq!(rolls, expected);
+        assert_eq!(((words.len() as f64).log2() * 10.0).round() / 10.0, 12.9);
+    }
+
+    #[test]
+    fn test_generate_passphrase() {
+        let mut ps = PasswordSpec::new(false, None);
+        ps.passphrase = Some(PassphraseSpec {
+            words: 6,
+            separator: " ".to_owned(),
+            capitalize: false,
+            digit: false,
+        });
+        let pw = ps.generate_hard_password().unwrap();
+        let words = wordlist();
+        assert_eq!(pw.deref().split(' ').count(), 6);
+        for word in pw.deref().split(' ') {
+            assert!(words.contains(&word));
+        }
+        assert_eq!(ps.entropy().round(), 78.0);
+
+        ps.passphrase = Some(PassphraseSpec {
+            words: 4,
+            separator: ".".to_owned(),
+            capitalize: true,
+            digit: true,
+        });
+        let pw = ps.generate_hard_password().unwrap();
+        assert_eq!(pw.deref().split('.').count(), 4);
+        assert!(pw
+            .deref()
+            .split('.')
+            .all(|word| word.starts_with(char::is_uppercase)));
+        assert_eq!(pw.deref().chars().filter(|c| c.is_ascii_digit()).count(), 1);
+        assert_eq!(ps.entropy().round(), 57.0);
+    }
+
     #[test]
     fn test_default_password_size_is_32() {
         assert_eq!(
@@ -131,4 +476,69 @@ mod test {
         }
         assert!(ok);
     }
+
+    #[test]
+    fn test_policy_parse() {
+        let mut policy = Policy::new(false, 32);
+        policy
+            .apply("length=12 upper=no symbol=3 symbols=@#$ exclude=abc no-ambiguous")
+            .unwrap();
+        assert_eq!(policy.length, 12);
+        assert_eq!(policy.lower, Some(1));
+        assert_eq!(policy.upper, None);
+        assert_eq!(policy.symbol, Some(3));
+        assert_eq!(policy.symbols, "@#$");
+        assert_eq!(policy.exclude, "abc");
+        assert!(policy.avoid_ambiguous);
+
+        assert!(Policy::new(false, 32).apply("length").is_err());
+        assert!(Policy::new(false, 32).apply("length=abc").is_err());
+        assert!(Policy::new(false, 32).apply("digit=maybe").is_err());
+        assert!(Policy::new(false, 32).apply("colour=blue").is_err());
+    }
+
+    #[test]
+    fn test_policy_check() {
+        assert!(Policy::new(false, 32).check().is_ok());
+        // Minimum counts that don't fit in the length
+        assert!(Policy::new(false, 8).apply("digit=5 symbol=4").is_err());
+        // A required class with all of its characters excluded
+        assert!(Policy::new(false, 8)
+            .apply("digit=1 exclude=0123456789")
+            .is_err());
+        // Nothing left at all
+        assert!(Policy::new(false, 8)
+            .apply("lower=no upper=no digit=no symbol=no")
+            .is_err());
+        // Only the included characters
+        assert!(Policy::new(false, 8)
+            .apply("lower=no upper=no digit=no symbol=no include=xyz")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_generate_password_with_policy() {
+        let mut ps = PasswordSpec::new(false, None);
+        ps.policy
+            .apply(
+                "length=20 lower=no upper=2 digit=10 symbol=3 symbols=@# exclude=AB no-ambiguous",
+            )
+            .unwrap();
+
+        for _ in 0..20 {
+            let pw = ps.generate_hard_password().unwrap();
+            assert_eq!(pw.len(), 20);
+            assert!(pw.chars().filter(|c| c.is_ascii_uppercase()).count() >= 2);
+            assert!(pw.chars().filter(|c| c.is_ascii_digit()).count() >= 10);
+            assert!(pw.chars().filter(|c| "@#".contains(*c)).count() >= 3);
+            for c in pw.chars() {
+                assert!(!c.is_ascii_lowercase());
+                assert!(!"AB0O1lI|".contains(c));
+                assert!(c.is_ascii_alphanumeric() || "@#".contains(c));
+            }
+        }
+
+        // 22 uppercase letters, 8 digits and 2 symbols
+        assert_eq!(ps.entropy().round(), (20.0 * 32f64.log2()).round());
+    }
 }
@@ -0,0 +1,161 @@
+//! Bitwarden, "Tools > Export vault" with the ".json" file format (not the encrypted one)
+
+use super::{
+    check_password, non_empty, parse_timestamp, set_otp, set_timestamps, Imported, Rejected,
+};
+use crate::password::v3::{Field, Password};
+use crate::rutil::safe_string::SafeString;
+use serde::Deserialize;
+
+/// Items of other types are secure notes, cards and identities
+const TYPE_LOGIN: u8 = 1;
+const FIELD_TYPE_HIDDEN: u8 = 1;
+/// Linked fields only point to another field, ie: the username
+const FIELD_TYPE_LINKED: u8 = 3;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    folders: Vec<Folder>,
+    /// Read one by one, so that one odd item doesn't prevent importing the others
+    #[serde(default)]
+    items: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct Folder {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: String,
+    notes: Option<String>,
+    folder_id: Option<String>,
+    fields: Option<Vec<ItemField>>,
+    login: Option<Login>,
+    creation_date: Option<String>,
+    revision_date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ItemField {
+    #[serde(rename = "type")]
+    kind: u8,
+    name: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    uris: Option<Vec<Uri>>,
+    username: Option<String>,
+    password: Option<String>,
+    totp: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Uri {
+    uri: Option<String>,
+}
+
+pub fn read(path: &str) -> Result<Imported, String> {
+    let file = std::fs::File::open(path).map_err(|err| err.to_string())?;
+    let export: Export = serde_json::from_reader(file).map_err(|err| err.to_string())?;
+    if export.encrypted {
+        return Err(String::from(
+            "This export is encrypted, export your vault again with the \".json\" file format",
+        ));
+    }
+
+    let mut imported = (vec![], vec![]);
+    for (i, item) in export.items.into_iter().enumerate() {
+        let name = item["name"].as_str().map(|name| name.to_owned());
+        let item: Item = match serde_json::from_value(item) {
+            Ok(item) => item,
+            Err(err) => {
+                let reason = format!("item {} is invalid ({})", i + 1, err);
+                imported
+                    .1
+                    .push(Rejected::new(None, name.as_deref(), reason));
+                continue;
+            }
+        };
+        let login = match item.login {
+            Some(login) if item.kind == TYPE_LOGIN => login,
+            _ => {
+                imported.1.push(Rejected::new(
+                    None,
+                    Some(&item.name),
+                    "only logins can be imported, not secure notes, cards or identities",
+                ));
+                continue;
+            }
+        };
+
+        let mut password = Password::new(
+            item.name,
+            non_empty(login.username.as_deref()).unwrap_or_default(),
+            login.password.unwrap_or_default(),
+        );
+        password.notes = non_empty(item.notes.as_deref());
+
+        let uris: Vec<String> = login
+            .uris
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|uri| non_empty(uri.uri.as_deref()))
+            .collect();
+        for (i, uri) in uris.into_iter().enumerate() {
+            if i == 0 {
+                password.url = Some(uri);
+            } else {
+                password.fields.push(Field {
+                    name: format!("URL {}", i + 1),
+                    value: SafeString::from_string(uri),
+                    secret: false,
+                });
+            }
+        }
+
+        let folder_id = item.folder_id;
+        if let Some(folder) = export
+            .folders
+            .iter()
+            .find(|folder| Some(&folder.id) == folder_id.as_ref())
+        {
+            password.tags.push(folder.name.clone());
+        }
+
+        for field in item.fields.unwrap_or_default() {
+            if field.kind == FIELD_TYPE_LINKED {
+                continue;
+            }
+            password.fields.push(Field {
+                name: non_empty(field.name.as_deref()).unwrap_or_else(|| String::from("Field")),
+                value: SafeString::from_string(field.value.unwrap_or_default()),
+                secret: field.kind == FIELD_TYPE_HIDDEN,
+            });
+        }
+
+        if let Some(totp) = non_empty(login.totp.as_deref()) {
+            set_otp(&mut password, &totp);
+        }
+
+        set_timestamps(
+            &mut password,
+            item.creation_date.as_deref().and_then(parse_timestamp),
+            item.revision_date.as_deref().and_then(parse_timestamp),
+        );
+
+        check_password(password, None, &mut imported);
+    }
+    Ok(imported)
+}
@@ -0,0 +1,266 @@
+//! CSV files with a header row, whose columns are found by name
+//!
+//! LastPass, Chrome and Firefox exports are read this way, and so is any other CSV file given
+//! a mapping with `rooster import csv --map name=Title,username=Login,password=Password`.
+
+use super::{
+    check_password, csv_line, csv_record, name_from_url, non_empty, parse_date, set_otp,
+    set_timestamps, split_tags, Imported, Rejected,
+};
+use crate::password::v3::Password;
+
+/// What a column is imported as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Name,
+    Username,
+    Password,
+    Url,
+    Notes,
+    Tags,
+    Otp,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl Target {
+    fn parse(target: &str) -> Result<Target, String> {
+        Ok(match target.trim().to_lowercase().as_str() {
+            "name" => Target::Name,
+            "username" => Target::Username,
+            "password" => Target::Password,
+            "url" => Target::Url,
+            "notes" => Target::Notes,
+            "tags" => Target::Tags,
+            "otp" => Target::Otp,
+            "created_at" => Target::CreatedAt,
+            "updated_at" => Target::UpdatedAt,
+            _ => {
+                return Err(format!(
+                    "\"{}\" is not something I can import, use one of name, username, password, url, notes, tags, otp, created_at or updated_at",
+                    target
+                ))
+            }
+        })
+    }
+}
+
+struct Column {
+    target: Target,
+    header: String,
+    /// Older exports may not have the column yet
+    optional: bool,
+}
+
+/// Which column of the file goes where
+pub struct Mapping {
+    columns: Vec<Column>,
+}
+
+impl Mapping {
+    /// Reads a mapping such as "name=Title,username=Login,password=Password"
+    pub fn parse(map: &str) -> Result<Mapping, String> {
+        let mut columns: Vec<Column> = Vec::new();
+        for pair in map.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (target, header) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("\"{}\" should look like name=Title", pair))?;
+            let name = target.trim();
+            let target = Target::parse(name)?;
+            if columns.iter().any(|column| column.target == target) {
+                return Err(format!("{} is mapped twice", name));
+            }
+            columns.push(Column {
+                target,
+                header: header.trim().to_owned(),
+                optional: false,
+            });
+        }
+
+        let has = |target| columns.iter().any(|column| column.target == target);
+        if !has(Target::Password) {
+            return Err(String::from("The password column must be mapped"));
+        }
+        if !has(Target::Name) && !has(Target::Url) {
+            return Err(String::from(
+                "The name column must be mapped, or the url column to name passwords after websites",
+            ));
+        }
+        Ok(Mapping { columns })
+    }
+
+    fn preset(required: &[(Target, &str)], optional: &[(Target, &str)]) -> Mapping {
+        let column = |optional| {
+            move |(target, header): &(Target, &str)| Column {
+                target: *target,
+                header: (*header).to_owned(),
+                optional,
+            }
+        };
+        Mapping {
+            columns: required
+                .iter()
+                .map(column(false))
+                .chain(optional.iter().map(column(true)))
+                .collect(),
+        }
+    }
+
+    /// LastPass, "Advanced Options > Export"
+    pub fn lastpass() -> Mapping {
+        Mapping::preset(
+            &[
+                (Target::Url, "url"),
+                (Target::Username, "username"),
+                (Target::Password, "password"),
+                (Target::Notes, "extra"),
+                (Target::Name, "name"),
+            ],
+            &[(Target::Tags, "grouping"), (Target::Otp, "totp")],
+        )
+    }
+
+    /// Chrome and browsers based on it, "Settings > Passwords > Export passwords"
+    pub fn chrome() -> Mapping {
+        Mapping::preset(
+            &[
+                (Target::Name, "name"),
+                (Target::Url, "url"),
+                (Target::Username, "username"),
+                (Target::Password, "password"),
+            ],
+            &[(Target::Notes, "note")],
+        )
+    }
+
+    /// Firefox, "about:logins > Export Logins", which names passwords after their website
+    pub fn firefox() -> Mapping {
+        Mapping::preset(
+            &[
+                (Target::Url, "url"),
+                (Target::Username, "username"),
+                (Target::Password, "password"),
+            ],
+            // Milliseconds since 1970
+            &[
+                (Target::CreatedAt, "timeCreated"),
+                (Target::UpdatedAt, "timePasswordChanged"),
+            ],
+        )
+    }
+}
+
+pub fn validate_map(map: &str) -> Result<(), String> {
+    Mapping::parse(map).map(|_| ())
+}
+
+pub fn read(path: &str, mapping: &Mapping) -> Result<Imported, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .map_err(|err| err.to_string())?;
+
+    let headers = reader.headers().map_err(|err| err.to_string())?.clone();
+    let mut indexes = Vec::new();
+    for column in mapping.columns.iter() {
+        let index = headers
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(&column.header));
+        match index {
+            Some(index) => indexes.push((column.target, index)),
+            None if column.optional => continue,
+            None => return Err(format!("The file has no \"{}\" column", column.header)),
+        }
+    }
+
+    let mut imported = (vec![], vec![]);
+    for record in reader.records() {
+        let record = match csv_record(record, &mut imported)? {
+            Some(record) => record,
+            None => continue,
+        };
+        let line = csv_line(&record);
+        let value = |target| {
+            indexes
+                .iter()
+                .find(|(t, _)| *t == target)
+                .and_then(|(_, index)| non_empty(record.get(*index)))
+        };
+
+        // LastPass puts "http://sn" as the URL of secure notes
+        let url = value(Target::Url).filter(|url| url != "http://sn");
+        let name = match value(Target::Name).or_else(|| url.as_deref().and_then(name_from_url)) {
+            Some(name) => name,
+            None => {
+                imported.1.push(Rejected::new(
+                    line,
+                    None,
+                    "no name and no URL to name it after",
+                ));
+                continue;
+            }
+        };
+        // Passwords may have spaces on purpose, they are kept as is
+        let secret = indexes
+            .iter()
+            .find(|(t, _)| *t == Target::Password)
+            .and_then(|(_, index)| record.get(*index))
+            .unwrap_or("");
+
+        let mut password = Password::new(name, value(Target::Username).unwrap_or_default(), secret);
+        password.url = url;
+        password.notes = value(Target::Notes);
+        password.tags = value(Target::Tags)
+            .map(|tags| split_tags(&tags))
+            .unwrap_or_default();
+        if let Some(otp) = value(Target::Otp) {
+            set_otp(&mut password, &otp);
+        }
+
+        let timestamp = |target| parse_date(value(target).as_deref());
+        match (timestamp(Target::CreatedAt), timestamp(Target::UpdatedAt)) {
+            (Ok(created_at), Ok(updated_at)) => {
+                set_timestamps(&mut password, created_at, updated_at)
+            }
+            (Err(reason), _) | (_, Err(reason)) => {
+                imported
+                    .1
+                    .push(Rejected::new(line, Some(&password.name), reason));
+                continue;
+            }
+        }
+
+        check_password(password, line, &mut imported);
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Mapping, Target};
+
+    #[test]
+    fn test_mapping_parse() {
+        let mapping = Mapping::parse("name=Title, username=Login,password=Pass word").unwrap();
+        let columns: Vec<(Target, &str)> = mapping
+            .columns
+            .iter()
+            .map(|column| (column.target, column.header.as_str()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                (Target::Name, "Title"),
+                (Target::Username, "Login"),
+                (Target::Password, "Pass word")
+            ]
+        );
+
+        assert!(Mapping::parse("url=Website,password=Password").is_ok());
+        assert!(Mapping::parse("name=Title").is_err());
+        assert!(Mapping::parse("username=Login,password=Password").is_err());
+        assert!(Mapping::parse("name=Title,password=Password,name=Other").is_err());
+        assert!(Mapping::parse("name=Title,password=Password,pin=PIN").is_err());
+        assert!(Mapping::parse("name,password=Password").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! KeePass and KeePassXC, "File > Export > KeePass XML (2.x)"
+//!
+//! KDBX databases themselves are compressed and encrypted with several schemes that Rooster
+//! doesn't implement, so they must be exported to XML first.
+
+use super::xml::{self, Element};
+use super::{
+    check_password, name_from_url, non_empty, parse_timestamp, set_otp, set_timestamps, Imported,
+};
+use crate::password::v3::{Field, Password};
+use crate::rutil::safe_string::SafeString;
+
+/// The first bytes of a KDBX database
+const KDBX_SIGNATURE: [u8; 4] = [0x03, 0xd9, 0xa2, 0x9a];
+
+/// The UUID of the recycle bin when there is none
+const NO_UUID: &str = "AAAAAAAAAAAAAAAAAAAAAA==";
+
+pub fn read(path: &str) -> Result<Imported, String> {
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    if bytes.starts_with(&KDBX_SIGNATURE) {
+        return Err(String::from(
+            "This is a KeePass database, export it to a \"KeePass XML (2.x)\" file first",
+        ));
+    }
+    let text = String::from_utf8(bytes).map_err(|err| err.to_string())?;
+    let document = xml::parse(&text)?;
+    let root = match document.child("Root") {
+        Some(root) if document.name == "KeePassFile" => root,
+        _ => return Err(String::from("This is not a KeePass XML export")),
+    };
+
+    // Deleted entries are kept in the recycle bin, they should stay deleted
+    let recycle_bin = document
+        .child("Meta")
+        .and_then(|meta| meta.child_text("RecycleBinUUID"))
+        .filter(|uuid| !uuid.is_empty() && *uuid != NO_UUID);
+
+    let mut imported = (vec![], vec![]);
+    // The top level group is the database itself, so its name is not a tag
+    for group in root.children("Group") {
+        read_group(group, None, recycle_bin, &mut imported);
+    }
+    Ok(imported)
+}
+
+fn read_group(
+    group: &Element,
+    tag: Option<&str>,
+    recycle_bin: Option<&str>,
+    imported: &mut Imported,
+) {
+    if recycle_bin.is_some() && group.child_text("UUID") == recycle_bin {
+        return;
+    }
+
+    for entry in group.children("Entry") {
+        check_password(read_entry(entry, tag), Some(entry.line), imported);
+    }
+
+    for subgroup in group.children("Group") {
+        read_group(subgroup, subgroup.child_text("Name"), recycle_bin, imported);
+    }
+}
+
+fn read_entry(entry: &Element, tag: Option<&str>) -> Password {
+    let mut password = Password::new("", "", "");
+    let mut otp = None;
+
+    // Entries keep their previous versions under "History", which are not read
+    for string in entry.children("String") {
+        let key = string.child_text("Key").unwrap_or("");
+        let value = match string.child("Value") {
+            Some(value) => value,
+            None => continue,
+        };
+        match key {
+            "Title" => password.name = value.text.trim().to_owned(),
+            "UserName" => password.username = value.text.trim().to_owned(),
+            "Password" => password.password = SafeString::from_string(value.text.clone()),
+            "URL" => password.url = non_empty(Some(&value.text)),
+            "Notes" => password.notes = non_empty(Some(&value.text)),
+            // KeePassXC, and KeePass since 2.47
+            "otp" | "TimeOtp-Secret-Base32" => otp = non_empty(Some(&value.text)),
+            _ => {
+                let secret = value.attribute("ProtectInMemory") == Some("True")
+                    || value.attribute("Protected") == Some("True");
+                password.fields.push(Field {
+                    name: key.to_owned(),
+                    value: SafeString::from_string(value.text.clone()),
+                    secret,
+                });
+            }
+        }
+    }
+
+    if password.name.is_empty() {
+        password.name = password
+            .url
+            .as_deref()
+            .and_then(name_from_url)
+            .unwrap_or_else(|| String::from("Untitled"));
+    }
+
+    password.tags.extend(tag.map(|tag| tag.to_owned()));
+    if let Some(tags) = entry.child_text("Tags") {
+        password.tags.extend(
+            tags.split([';', ','])
+                .map(|tag| tag.trim())
+                .filter(|tag| !tag.is_empty())
+                .map(|tag| tag.to_owned()),
+        );
+    }
+
+    if let Some(otp) = otp {
+        set_otp(&mut password, &otp);
+    }
+
+    if let Some(times) = entry.child("Times") {
+        set_timestamps(
+            &mut password,
+            times.child_text("CreationTime").and_then(parse_timestamp),
+            times
+                .child_text("LastModificationTime")
+                .and_then(parse_timestamp),
+        );
+    }
+
+    password
+}
@@ -0,0 +1,282 @@
+//! Readers for the exports of other password managers
+//!
+//! Each reader returns the passwords it could read, and the ones it can't import, ie: credit
+//! cards or malformed lines, so that they can be reported. They keep going past these.
+
+pub mod bitwarden;
+pub mod csv_map;
+pub mod keepass;
+pub mod pass;
+mod xml;
+
+use crate::ffi;
+use crate::password::v3::{Field, Password};
+use crate::rutil::safe_string::SafeString;
+use crate::totp::Otp;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Something that can't be imported, and why
+#[derive(Debug)]
+pub struct Rejected {
+    /// Where it is in the file, for formats made of lines
+    pub line: Option<u64>,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
+impl Rejected {
+    pub fn new(line: Option<u64>, name: Option<&str>, reason: impl ToString) -> Rejected {
+        Rejected {
+            line,
+            name: non_empty(name),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Rejected {
+    /// ie: "Line 3, Youtube: no password"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.line, &self.name) {
+            (Some(line), Some(name)) => write!(f, "Line {}, {}: {}", line, name, self.reason),
+            (Some(line), None) => write!(f, "Line {}: {}", line, self.reason),
+            (None, Some(name)) => write!(f, "{}: {}", name, self.reason),
+            (None, None) => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// Passwords that can be imported, and the ones that can't
+pub type Imported = (Vec<Password>, Vec<Rejected>);
+
+/// Reads the next record of a CSV file
+///
+/// Malformed records are rejected so that the next ones can still be read, but a file that can't
+/// be read at all is an error.
+pub fn csv_record(
+    result: csv::Result<csv::StringRecord>,
+    imported: &mut Imported,
+) -> Result<Option<csv::StringRecord>, String> {
+    match result {
+        Ok(record) => Ok(Some(record)),
+        Err(err) if err.is_io_error() => Err(err.to_string()),
+        Err(err) => {
+            let line = err.position().map(|position| position.line());
+            imported.1.push(Rejected::new(line, None, err));
+            Ok(None)
+        }
+    }
+}
+
+/// The line of a CSV record, for error messages
+pub fn csv_line(record: &csv::StringRecord) -> Option<u64> {
+    record.position().map(|position| position.line())
+}
+
+/// Passwords need one, the rest is optional
+pub fn check_password(password: Password, line: Option<u64>, imported: &mut Imported) {
+    if password.password.is_empty() {
+        imported
+            .1
+            .push(Rejected::new(line, Some(&password.name), "no password"));
+    } else {
+        imported.0.push(password);
+    }
+}
+
+/// Splits comma separated tags, as written by `rooster export csv`
+pub fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| tag.to_owned())
+        .collect()
+}
+
+/// Names a password after the website it's for, when the export doesn't name it
+///
+/// ie: "https://www.youtube.com/login" gives "youtube.com"
+pub fn name_from_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    let without_scheme = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+    let authority = without_scheme.split(['/', '?', '#']).next().unwrap_or("");
+    let host = authority.rsplit('@').next().unwrap_or("");
+    let host = host.split(':').next().unwrap_or("");
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    Some(host.to_lowercase()).filter(|host| !host.is_empty())
+}
+
+/// Keeps optional text only when there is some
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_owned())
+}
+
+/// Sets the secret for two factor authentication codes, or keeps it in a secret field if Rooster
+/// can't generate codes with it, ie: Steam Guard secrets
+fn set_otp(password: &mut Password, otp: &str) {
+    match Otp::parse(otp) {
+        Ok(otp) => password.otp = Some(otp),
+        Err(_) => password.fields.push(Field {
+            name: String::from("OTP"),
+            value: SafeString::from_string(otp.to_owned()),
+            secret: true,
+        }),
+    }
+}
+
+/// Days between 1970-01-01 and a date, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Reads a date as exported by password managers, ie: "2020-11-16T19:16:09.123Z",
+/// "2020-11-16 19:16:09", "2020-11-16", or a Unix timestamp in seconds or milliseconds
+pub fn parse_timestamp(value: &str) -> Option<ffi::time_t> {
+    let value = value.trim();
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        let timestamp: u64 = value.parse().ok()?;
+        // Seconds would only get this big in the year 5138
+        let timestamp = if timestamp >= 100_000_000_000 {
+            timestamp / 1000
+        } else {
+            timestamp
+        };
+        return ffi::time_t::try_from(timestamp).ok();
+    }
+
+    let number = |part: &str| -> Option<i64> {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        part.parse().ok()
+    };
+
+    let date = value.get(..10)?;
+    let mut date_parts = date.split('-');
+    let year = number(date_parts.next()?)?;
+    let month = number(date_parts.next()?)?;
+    let day = number(date_parts.next()?)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86400;
+
+    let rest = &value[10..];
+    if let Some(time) = rest.strip_prefix('T').or_else(|| rest.strip_prefix(' ')) {
+        // The offset from UTC, if any, comes after the time
+        let offset_start = time.find(['Z', '+', '-']);
+        let (clock, offset) = time.split_at(offset_start.unwrap_or(time.len()));
+        let clock = clock.split('.').next()?;
+        let mut clock_parts = clock.split(':');
+        seconds += number(clock_parts.next()?)? * 3600;
+        seconds += number(clock_parts.next()?)? * 60;
+        seconds += clock_parts.next().map_or(Some(0), number)?;
+
+        if let Some(sign) = offset.chars().next().filter(|c| *c != 'Z') {
+            let offset = offset[1..].replace(':', "");
+            let hours = number(offset.get(..2)?)?;
+            let minutes = number(offset.get(2..)?).unwrap_or(0);
+            let offset = hours * 3600 + minutes * 60;
+            seconds += if sign == '+' { -offset } else { offset };
+        }
+    } else if !rest.is_empty() {
+        return None;
+    }
+
+    ffi::time_t::try_from(seconds).ok()
+}
+
+/// Reads an optional date column, an empty one is unknown
+pub fn parse_date(value: Option<&str>) -> Result<Option<ffi::time_t>, String> {
+    match value.map(str::trim).filter(|date| !date.is_empty()) {
+        None => Ok(None),
+        Some(date) => parse_timestamp(date)
+            .map(Some)
+            .ok_or_else(|| format!("\"{}\" is not a date", date)),
+    }
+}
+
+/// Keeps the dates of the export, which tell how old the passwords are
+///
+/// A password can't be updated before it was created, so if only one date is known, it is
+/// used for both.
+pub fn set_timestamps(
+    password: &mut Password,
+    created_at: Option<ffi::time_t>,
+    updated_at: Option<ffi::time_t>,
+) {
+    match (created_at, updated_at) {
+        (None, None) => {}
+        (Some(created_at), None) => {
+            password.created_at = created_at;
+            password.updated_at = created_at;
+        }
+        (None, Some(updated_at)) => {
+            password.created_at = updated_at;
+            password.updated_at = updated_at;
+        }
+        (Some(created_at), Some(updated_at)) => {
+            password.created_at = created_at.min(updated_at);
+            password.updated_at = updated_at;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{name_from_url, parse_timestamp};
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1605554169"), Some(1605554169));
+        assert_eq!(parse_timestamp("1605554169000"), Some(1605554169));
+        assert_eq!(parse_timestamp("2020-11-16T19:16:09Z"), Some(1605554169));
+        assert_eq!(
+            parse_timestamp("2020-11-16T19:16:09.123Z"),
+            Some(1605554169)
+        );
+        assert_eq!(parse_timestamp("2020-11-16 19:16:09"), Some(1605554169));
+        assert_eq!(
+            parse_timestamp("2020-11-16T20:16:09+01:00"),
+            Some(1605554169)
+        );
+        assert_eq!(parse_timestamp("2020-11-16T18:16-0100"), Some(1605554160));
+        assert_eq!(parse_timestamp("2020-11-16"), Some(1605484800));
+        assert_eq!(parse_timestamp("2000-02-29"), Some(951782400));
+        assert_eq!(parse_timestamp("1969-12-31"), None);
+        assert_eq!(parse_timestamp("2020-13-16"), None);
+        assert_eq!(parse_timestamp("2020-11-16 late"), None);
+        assert_eq!(parse_timestamp("yesterday"), None);
+        assert_eq!(parse_timestamp(""), None);
+    }
+
+    #[test]
+    fn test_name_from_url() {
+        assert_eq!(
+            name_from_url("https://www.youtube.com/login"),
+            Some(String::from("youtube.com"))
+        );
+        assert_eq!(
+            name_from_url("ftp://me@Example.org:21"),
+            Some(String::from("example.org"))
+        );
+        assert_eq!(
+            name_from_url("github.com?x=1"),
+            Some(String::from("github.com"))
+        );
+        assert_eq!(name_from_url("https://"), None);
+        assert_eq!(name_from_url(""), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! pass, the standard unix password manager, which keeps each password in its own file encrypted
+//! with GnuPG, ie: `~/.password-store/email/gmail.com.gpg`
+
+use super::{check_password, non_empty, set_otp, set_timestamps, Imported, Rejected};
+use crate::ffi;
+use crate::password::v3::Password;
+use crate::rutil::safe_string::SafeString;
+use crate::rutil::safe_vec::SafeVec;
+use std::convert::TryFrom;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const DEFAULT_DECRYPT_COMMAND: &str = "gpg --quiet --batch --decrypt";
+
+pub fn read(directory: &str, decrypt_command: &str) -> Result<Imported, String> {
+    let directory = Path::new(directory);
+    let mut files = Vec::new();
+    find_files(directory, &mut files).map_err(|err| err.to_string())?;
+    files.sort();
+
+    let mut imported = (vec![], vec![]);
+    for file in files {
+        // "email/gmail.com.gpg" is named "email/gmail.com"
+        let name = file
+            .strip_prefix(directory)
+            .unwrap_or(&file)
+            .with_extension("")
+            .to_string_lossy()
+            .into_owned();
+
+        match decrypt(decrypt_command, &file) {
+            Ok(content) => {
+                let mut password = parse_entry(&name, &content);
+                // pass doesn't keep dates, but the file changes with the password
+                let modified = modified_at(&file);
+                set_timestamps(&mut password, None, modified);
+                check_password(password, None, &mut imported);
+            }
+            // Without GnuPG, no file can be decrypted
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Err(format!("could not run `{}` ({})", decrypt_command, err))
+            }
+            Err(err) => imported.1.push(Rejected::new(None, Some(&name), err)),
+        }
+    }
+    Ok(imported)
+}
+
+fn modified_at(file: &Path) -> Option<ffi::time_t> {
+    let modified = std::fs::metadata(file).ok()?.modified().ok()?;
+    let seconds = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    ffi::time_t::try_from(seconds).ok()
+}
+
+/// Finds the encrypted files, leaving out the `.git` directory and other hidden files
+fn find_files(directory: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_none_or(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            find_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "gpg") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Runs the command with the path of the file as its last argument, and reads its output
+///
+/// GnuPG may ask for the passphrase of the key on the TTY.
+fn decrypt(command: &str, file: &Path) -> std::io::Result<SafeString> {
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("gpg");
+    let output = Command::new(program)
+        .args(words)
+        .arg(file)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    let content = SafeVec::new(output.stdout);
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "`{}` failed with {}",
+            command, output.status
+        )));
+    }
+    let content = std::str::from_utf8(&content)
+        .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+    Ok(SafeString::from_string(content.to_owned()))
+}
+
+/// The password is on the first line, and the next lines hold the rest by convention, ie:
+/// "login: me@example.com", "url: https://example.com" or an "otpauth://" URI
+fn parse_entry(name: &str, content: &str) -> Password {
+    let mut lines = content.lines();
+    let mut password = Password::new(name, "", lines.next().unwrap_or(""));
+    let mut notes = Vec::new();
+
+    for line in lines {
+        if line.trim_start().starts_with("otpauth://") {
+            set_otp(&mut password, line);
+            continue;
+        }
+        let (key, value) = line.split_once(':').unwrap_or(("", ""));
+        match key.trim().to_lowercase().as_str() {
+            "login" | "username" | "user" if password.username.is_empty() => {
+                password.username = value.trim().to_owned();
+            }
+            "url" | "website" if password.url.is_none() => {
+                password.url = non_empty(Some(value));
+            }
+            _ => notes.push(line),
+        }
+    }
+
+    password.notes = non_empty(Some(&notes.join("\n")));
+    password
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_entry;
+    use std::ops::Deref;
+
+    #[test]
+    fn test_parse_entry() {
+        let password = parse_entry(
+            "email/gmail.com",
+            "p4ss: word\nlogin: me@gmail.com\nURL: https://mail.google.com\nRecovery code: 1234\n\nThe old one was leaked\n",
+        );
+        assert_eq!(password.name, "email/gmail.com");
+        assert_eq!(password.password.deref(), "p4ss: word");
+        assert_eq!(password.username, "me@gmail.com");
+        assert_eq!(password.url.as_deref(), Some("https://mail.google.com"));
+        assert_eq!(
+            password.notes.as_deref(),
+            Some("Recovery code: 1234\n\nThe old one was leaked")
+        );
+
+        let password = parse_entry(
+            "github.com",
+            "abcd\notpauth://totp/GitHub:me?secret=JBSWY3DPEHPK3PXP",
+        );
+        assert!(password.otp.is_some());
+        assert_eq!(password.notes, None);
+    }
+}
@@ -0,0 +1,276 @@
+//! Just enough XML to read the exports of other password managers
+//!
+//! Namespaces, DTDs and processing instructions are not supported, they are skipped.
+
+/// An element, with its text and child elements
+#[derive(Debug, Default)]
+pub struct Element {
+    pub name: String,
+    /// Where the element starts in the document
+    pub line: u64,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// All the text directly inside the element, entities decoded
+    pub text: String,
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first child element with this name
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The text of the first child element with this name
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.as_str())
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    /// The line at `counted`, so that lines are counted only once
+    line: u64,
+    counted: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn line(&mut self) -> u64 {
+        self.line += self.input[self.counted..self.position]
+            .matches('\n')
+            .count() as u64;
+        self.counted = self.position;
+        self.line
+    }
+
+    fn error(&mut self, message: &str) -> String {
+        format!("{} on line {}", message, self.line())
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips everything up to and including `end`
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, String> {
+        match self.rest().find(end) {
+            Some(index) => {
+                let skipped = &self.rest()[..index];
+                self.position += index + end.len();
+                Ok(skipped)
+            }
+            None => Err(self.error(&format!("Expected \"{}\"", end))),
+        }
+    }
+
+    /// Skips the XML declaration, comments, doctypes and processing instructions
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") && !self.rest().starts_with("<![CDATA[") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=')
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error("Expected a name"));
+        }
+        self.position += length;
+        Ok(rest[..length].to_owned())
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("Expected an element"));
+        }
+        let line = self.line();
+        self.position += 1;
+
+        let mut element = Element {
+            name: self.name()?,
+            line,
+            ..Element::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error("Expected \"=\" after an attribute name"));
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => return Err(self.error("Expected a quoted attribute value")),
+            };
+            self.position += 1;
+            let value = self.skip_past(&quote.to_string())?;
+            element
+                .attributes
+                .push((name, decode_entities(value).map_err(|e| self.error(&e))?));
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                self.position += 2;
+                let name = self.name()?;
+                if name != element.name {
+                    return Err(self.error(&format!(
+                        "Expected \"</{}>\", found \"</{}>\"",
+                        element.name, name
+                    )));
+                }
+                self.skip_whitespace();
+                self.skip_past(">")?;
+                return Ok(element);
+            } else if rest.starts_with("<![CDATA[") {
+                self.position += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?;
+                element.text.push_str(text);
+            } else if rest.starts_with("<!--") || rest.starts_with("<?") {
+                self.skip_misc()?;
+            } else if rest.starts_with('<') {
+                element.children.push(self.element()?);
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("Expected \"</{}>\"", element.name)));
+            } else {
+                let length = rest.find('<').unwrap_or(rest.len());
+                let text = decode_entities(&rest[..length]).map_err(|e| self.error(&e))?;
+                element.text.push_str(&text);
+                self.position += length;
+            }
+        }
+    }
+}
+
+fn decode_entities(input: &str) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(';')
+            .ok_or_else(|| String::from("Unterminated entity"))?;
+        let entity = &rest[start + 1..start + end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(std::char::from_u32),
+        };
+        match decoded {
+            Some(c) => output.push(c),
+            None => return Err(format!("Unknown entity \"&{};\"", entity)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Parses a document, and returns its root element
+pub fn parse(input: &str) -> Result<Element, String> {
+    let mut parser = Parser {
+        input: input.strip_prefix('\u{feff}').unwrap_or(input),
+        position: 0,
+        line: 1,
+        counted: 0,
+    };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if !parser.rest().is_empty() {
+        return Err(parser.error("Unexpected content after the root element"));
+    }
+    Ok(root)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let root = parse(
+            "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n\
+             <!-- exported -->\n\
+             <Root a='1' b=\"x &amp; y\">\n\
+               <Entry><Key>Title</Key><Value Protected=\"True\">a &lt;b&gt; &#233;&#x41;</Value></Entry>\n\
+               <Entry><Key>Notes</Key><Value><![CDATA[<not> & parsed]]></Value></Entry>\n\
+               <Empty/>\n\
+             </Root>\n",
+        )
+        .unwrap();
+
+        assert_eq!(root.name, "Root");
+        assert_eq!(root.line, 3);
+        assert_eq!(root.child("Empty").unwrap().line, 6);
+        assert_eq!(root.attribute("a"), Some("1"));
+        assert_eq!(root.attribute("b"), Some("x & y"));
+        assert_eq!(root.children("Entry").count(), 2);
+        let value = root.child("Entry").unwrap().child("Value").unwrap();
+        assert_eq!(value.text, "a <b> éA");
+        assert_eq!(value.attribute("Protected"), Some("True"));
+        assert_eq!(
+            root.children("Entry").nth(1).unwrap().child_text("Value"),
+            Some("<not> & parsed")
+        );
+        assert!(root.child("Empty").unwrap().children.is_empty());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("<a><b></a>").is_err());
+        assert!(parse("<a>").is_err());
+        assert!(parse("<a>&nope;</a>").is_err());
+        assert!(parse("<a></a><b></b>").is_err());
+        assert!(parse("").is_err());
+    }
+}
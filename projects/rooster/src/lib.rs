@@ -1,6 +1,6 @@
 // #![allow(useless_format, too_many_arguments)]
 
-use crate::password::v2::PasswordStore;
+use crate::password::v3::PasswordStore;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 use crate::rutil::safe_string::SafeString;
@@ -14,11 +14,20 @@ use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
 mod aes;
+#[cfg(unix)]
+mod agent;
+mod bundle;
 mod clip;
 mod commands;
+mod details;
 mod ffi;
+mod fuzzy;
 mod generate;
+mod importers;
 mod list;
+mod lock;
+mod master_password;
+mod output;
 mod password;
 mod quale;
 #[allow(unused)]
@@ -29,7 +38,8 @@ mod rpassword;
 mod rprompt;
 #[allow(unused)]
 mod rutil;
-mod shell_escape;
+mod safe_file;
+mod totp;
 
 fn validate_arg_digits(v: &str) -> Result<(), String> {
     if v.chars()
@@ -42,6 +52,110 @@ fn validate_arg_digits(v: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Arguments of every `import` subcommand
+fn import_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("on-conflict")
+            .long("on-conflict")
+            .takes_value(true)
+            .possible_values(["skip", "overwrite", "newer", "rename", "ask"])
+            .default_value("skip")
+            .help("What to do with passwords already in the password store: keep them, overwrite them, overwrite them with newer ones, import under another name or ask"),
+        Arg::new("dry-run")
+            .long("dry-run")
+            .help("Show what would be imported without changing the password store"),
+    ]
+}
+
+/// Arguments to set the URL, notes, tags and custom fields of a password
+fn details_args(change: bool) -> Vec<Arg<'static>> {
+    let mut args = vec![
+        Arg::new("url")
+            .long("url")
+            .takes_value(true)
+            .help("The address of the website"),
+        Arg::new("notes")
+            .long("notes")
+            .takes_value(true)
+            .help("Free form notes"),
+        Arg::new("tag")
+            .long("tag")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .help("A tag, can be repeated"),
+        Arg::new("field")
+            .long("field")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("name=value")
+            .validator(details::validate_field)
+            .help("A custom field, can be repeated"),
+        Arg::new("secret-field")
+            .long("secret-field")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .value_name("name=value")
+            .validator(details::validate_field)
+            .help("A custom field that is hidden unless asked for, can be repeated"),
+        Arg::new("otp")
+            .long("otp")
+            .takes_value(true)
+            .value_name("uri or secret")
+            .validator(details::validate_otp)
+            .help("The two factor authentication secret, as an otpauth:// URI or in base32"),
+    ];
+    if change {
+        args.push(
+            Arg::new("remove-field")
+                .long("remove-field")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_name("name")
+                .help("Remove a custom field, can be repeated"),
+        );
+    }
+    args
+}
+
+/// Arguments to generate a passphrase instead of random characters
+fn generation_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("words")
+            .short('w')
+            .long("words")
+            .takes_value(true)
+            .help("Generate a passphrase of this many words instead of random characters")
+            .validator(validate_arg_digits),
+        Arg::new("separator")
+            .long("separator")
+            .takes_value(true)
+            .requires("words")
+            .help("The separator between the words of the passphrase, \"-\" by default"),
+        Arg::new("capitalize")
+            .long("capitalize")
+            .requires("words")
+            .help("Capitalize the words of the passphrase"),
+        Arg::new("digit")
+            .long("digit")
+            .requires("words")
+            .help("Add a random digit to one of the words of the passphrase"),
+        Arg::new("policy")
+            .long("policy")
+            .takes_value(true)
+            .conflicts_with("words")
+            .value_name("rules")
+            .help("Rules for the characters of the password, ie: \"length=16 symbol=2 symbols=@#$ no-ambiguous\". Kept for `regenerate`"),
+    ]
+}
+
+fn selection_arg() -> Arg<'static> {
+    Arg::new("selection")
+        .long("selection")
+        .takes_value(true)
+        .possible_values(["clipboard", "primary", "both"])
+        .help("Copy to the clipboard, to the primary selection (pasted with a middle click) or to both")
+}
+
 fn open_password_file(filename: &str) -> IoResult<File> {
     let mut options = std::fs::OpenOptions::new();
     options.read(true);
@@ -50,20 +164,77 @@ fn open_password_file(filename: &str) -> IoResult<File> {
     options.open(&Path::new(filename))
 }
 
-fn create_password_file(filename: &str) -> IoResult<File> {
-    let mut options = std::fs::OpenOptions::new();
-    options.read(true);
-    options.write(true);
-    options.create(true);
-    options.open(&Path::new(filename))
+type CommandCallback<IO> = fn(&clap::ArgMatches, &mut PasswordStore, &mut IO) -> Result<(), i32>;
+
+fn lock_password_file(
+    rooster_file_path: &Path,
+    mode: lock::LockMode,
+    wait: bool,
+    io: &mut impl CliInputOutput,
+) -> Result<lock::Lock, i32> {
+    match lock::lock(rooster_file_path, mode, wait) {
+        Ok(lock) => Ok(lock),
+        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+            io.error(
+                "Woops, another Rooster is using your password file right now. Try again once it's done, or use `--wait` to wait for it.",
+                OutputType::Error,
+            );
+            Err(1)
+        }
+        Err(err) => {
+            io.error(
+                format!(
+                    "Woops, I couldn't lock your password file (reason: {}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
+
+/// Whether the password file has an older format, which is upgraded once it's opened
+///
+/// Rooster never goes back to an older format, so this stays true until the file is upgraded.
+fn needs_upgrade(rooster_file_path: &Path) -> bool {
+    let mut version = [0u8; 4];
+    match File::open(rooster_file_path).and_then(|mut file| file.read_exact(&mut version)) {
+        Ok(()) => u32::from_be_bytes(version) < password::v3::VERSION,
+        Err(_) => false,
+    }
 }
 
 fn sync_password_store(
     store: &mut PasswordStore,
-    file: &mut File,
+    rooster_file_path: &Path,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
-    if let Err(err) = store.sync(file) {
+    let backup_count =
+        match safe_file::parse_backup_count(env::var("ROOSTER_BACKUPS").ok().as_deref()) {
+            Ok(count) => count,
+            Err(err) => {
+                io.error(
+                    format!("I could not save the password file (reason: {}).", err),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        };
+
+    if let Err(err) = safe_file::rotate_backups(rooster_file_path, backup_count) {
+        io.error(
+            format!(
+                "I could not make a backup of the password file (reason: {:?}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    if let Err(err) = store.sync(rooster_file_path) {
+        output::remember_error(&err);
         io.error(
             format!("I could not save the password file (reason: {:?}).", err),
             OutputType::Error,
@@ -76,22 +247,64 @@ fn sync_password_store(
 
 fn get_password_store(
     file: &mut File,
+    source: &master_password::Source,
+    use_agent: bool,
     io: &mut impl CliInputOutput,
-) -> Result<password::v2::PasswordStore, i32> {
+) -> Result<password::v3::PasswordStore, i32> {
     // Read the Rooster file contents.
     let mut input: SafeVec = SafeVec::new(Vec::new());
     file.read_to_end(input.inner_mut()).map_err(|_| 1)?;
 
-    return get_password_store_from_input_interactive(&input, 3, false, false, io).map_err(|_| 1);
+    // If the agent is running and already knows the key, no need to ask for the master password.
+    if use_agent {
+        if let Some(store) = open_with_agent(&input) {
+            return Ok(store);
+        }
+    }
+
+    let store = get_password_store_from_input_interactive(&input, 3, false, None, source, io)
+        .map_err(|err| {
+            output::remember_error(&err);
+            1
+        })?;
+    add_key_to_agent(&store);
+    Ok(store)
+}
+
+#[cfg(unix)]
+fn open_with_agent(input: &SafeVec) -> Option<PasswordStore> {
+    let json_schema = agent::decrypt(input)?;
+    PasswordStore::from_agent(input.clone(), json_schema).ok()
+}
+
+#[cfg(not(unix))]
+fn open_with_agent(_input: &SafeVec) -> Option<PasswordStore> {
+    None
+}
+
+/// Hands the key over to the agent, so that the next commands don't need the master password
+#[cfg(unix)]
+fn add_key_to_agent(store: &PasswordStore) {
+    if let Some(key) = store.key() {
+        agent::add_key(key);
+    }
 }
 
+#[cfg(not(unix))]
+fn add_key_to_agent(_store: &PasswordStore) {}
+
+/// Opens the password store, asking for the master password again if it's wrong
+///
+/// `master_password` is given when it was read already, ie: before asking to upgrade the file.
+/// Non-interactive sources can't always be read twice, ie: a pipe is empty once read.
 fn get_password_store_from_input_interactive(
     input: &SafeVec,
     retries: i32,
     force_upgrade: bool,
-    retry: bool,
+    master_password: Option<SafeString>,
+    source: &master_password::Source,
     io: &mut impl CliInputOutput,
-) -> Result<password::v2::PasswordStore, password::PasswordError> {
+) -> Result<password::v3::PasswordStore, password::PasswordError> {
     if retries == 0 {
         io.error(
             "Decryption of your Rooster file keeps failing. \
@@ -101,14 +314,7 @@ fn get_password_store_from_input_interactive(
         return Err(password::PasswordError::CorruptionLikelyError);
     }
 
-    if retry {
-        io.error(
-            "Woops, that's not the right password. Let's try again.",
-            OutputType::Error,
-        );
-    }
-
-    let master_password = match ask_master_password(io) {
+    let master_password = match master_password.map_or_else(|| source.read(io), Ok) {
         Ok(p) => p,
         Err(err) => {
             io.error(
@@ -149,40 +355,73 @@ fn get_password_store_from_input_interactive(
             return Err(password::PasswordError::Io(err));
         }
         Err(password::PasswordError::NeedUpgradeErrorFromV1) => {
-            io.error("Your Rooster file has version 1. You need to upgrade to version 2.\n\nWARNING: If in doubt, it could mean you've been hacked. Only \
-                 proceed if you recently upgraded your Rooster installation.\nUpgrade to version 2? [y/n]", OutputType::Error
+            ask_upgrade(input, retries, 1, master_password, source, io)
+        }
+        Err(password::PasswordError::NeedUpgradeErrorFromV2) => {
+            ask_upgrade(input, retries, 2, master_password, source, io)
+        }
+        // Reading the same password again from a file or a command wouldn't help.
+        Err(err) if !source.is_interactive() => {
+            io.error("Woops, that's not the right password.", OutputType::Error);
+            Err(err)
+        }
+        _ => {
+            io.warning(
+                "Woops, that's not the right password. Let's try again.",
+                OutputType::Error,
             );
-            loop {
-                match io.read_line() {
-                    Ok(line) => {
-                        if line.starts_with('y') {
-                            // This time we'll try to upgrade
-                            return get_password_store_from_input_interactive(
-                                &input, retries, true, false, io,
-                            );
-                        } else if line.starts_with('n') {
-                            // The user doesn't want to upgrade, that's fine
-                            return Err(password::PasswordError::NoUpgradeError);
-                        } else {
-                            io.error(
-                                "I did not get that. Upgrade from v1 to v2? [y/n]",
-                                OutputType::Error,
-                            );
-                        }
-                    }
-                    Err(io_err) => {
-                        io.error(format!(
-                                "Woops, an error occured while reading your response (reason: {:?}).",
-                                io_err
-                            ), OutputType::Error,
-                        );
-                        return Err(password::PasswordError::Io(io_err));
-                    }
+            get_password_store_from_input_interactive(input, retries - 1, false, None, source, io)
+        }
+    }
+}
+
+fn ask_upgrade(
+    input: &SafeVec,
+    retries: i32,
+    from_version: u32,
+    master_password: SafeString,
+    source: &master_password::Source,
+    io: &mut impl CliInputOutput,
+) -> Result<password::v3::PasswordStore, password::PasswordError> {
+    io.warning(format!("Your Rooster file has version {}. You need to upgrade to version 3.\n\nWARNING: If in doubt, it could mean you've been hacked. Only \
+         proceed if you recently upgraded your Rooster installation.\nUpgrade to version 3? [y/n]", from_version), OutputType::Error
+    );
+    loop {
+        match io.read_line() {
+            Ok(line) => {
+                if line.starts_with('y') {
+                    // This time we'll try to upgrade
+                    return get_password_store_from_input_interactive(
+                        input,
+                        retries,
+                        true,
+                        Some(master_password),
+                        source,
+                        io,
+                    );
+                } else if line.starts_with('n') {
+                    // The user doesn't want to upgrade, that's fine
+                    return Err(password::PasswordError::NoUpgradeError);
+                } else {
+                    io.warning(
+                        format!(
+                            "I did not get that. Upgrade from v{} to v3? [y/n]",
+                            from_version
+                        ),
+                        OutputType::Error,
+                    );
                 }
             }
-        }
-        _ => {
-            return get_password_store_from_input_interactive(&input, retries - 1, false, true, io);
+            Err(io_err) => {
+                io.error(
+                    format!(
+                        "Woops, an error occured while reading your response (reason: {:?}).",
+                        io_err
+                    ),
+                    OutputType::Error,
+                );
+                return Err(password::PasswordError::Io(io_err));
+            }
         }
     }
 }
@@ -191,9 +430,9 @@ fn get_password_store_from_input(
     input: &SafeVec,
     master_password: &SafeString,
     upgrade: bool,
-) -> Result<password::v2::PasswordStore, password::PasswordError> {
+) -> Result<password::v3::PasswordStore, password::PasswordError> {
     // Try to open the file as is.
-    match password::v2::PasswordStore::from_input(master_password.clone(), input.clone()) {
+    match password::v3::PasswordStore::from_input(master_password.clone(), input.clone()) {
         Ok(store) => {
             return Ok(store);
         }
@@ -203,9 +442,10 @@ fn get_password_store_from_input(
         Err(password::PasswordError::OutdatedRoosterBinaryError) => {
             return Err(password::PasswordError::OutdatedRoosterBinaryError);
         }
-        Err(password::PasswordError::NeedUpgradeErrorFromV1) => {
+        Err(err @ password::PasswordError::NeedUpgradeErrorFromV1)
+        | Err(err @ password::PasswordError::NeedUpgradeErrorFromV2) => {
             if !upgrade {
-                return Err(password::PasswordError::NeedUpgradeErrorFromV1);
+                return Err(err);
             }
 
             // If we can't open the file, we may need to upgrade its format first.
@@ -224,10 +464,6 @@ fn get_password_store_from_input(
     }
 }
 
-fn ask_master_password(io: &mut impl CliInputOutput) -> IoResult<SafeString> {
-    io.prompt_password("Type your master password: ")
-}
-
 pub fn main_with_args(
     args: &[&str],
     io: &mut impl CliInputOutput,
@@ -239,6 +475,61 @@ pub fn main_with_args(
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .about("Welcome to Rooster, the simple password manager for geeks :-)")
         .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("wait")
+                .long("wait")
+                .global(true)
+                .help("Wait for other Rooster commands using the password file to finish"),
+        )
+        .arg(
+            Arg::new("clear-after")
+                .long("clear-after")
+                .global(true)
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(validate_arg_digits)
+                .help("Clear the clipboard this many seconds after copying a password to it, 30 by default"),
+        )
+        .arg(
+            Arg::new("no-clear")
+                .long("no-clear")
+                .global(true)
+                .conflicts_with("clear-after")
+                .help("Leave copied passwords in the clipboard"),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .global(true)
+                .takes_value(true)
+                .possible_values(["text", "json"])
+                .default_value("text")
+                .help("Print results as sentences, or as a JSON document for scripts"),
+        )
+        .arg(
+            Arg::new("password-fd")
+                .long("password-fd")
+                .global(true)
+                .takes_value(true)
+                .value_name("fd")
+                .validator(validate_arg_digits)
+                .help("Read the master password from this file descriptor instead of asking for it"),
+        )
+        .arg(
+            Arg::new("password-file")
+                .long("password-file")
+                .global(true)
+                .takes_value(true)
+                .value_name("path")
+                .conflicts_with("password-fd")
+                .help("Read the master password from the first line of this file instead of asking for it"),
+        )
+        .arg(
+            Arg::new("osc52")
+                .long("osc52")
+                .global(true)
+                .help("Copy to the clipboard of your terminal with an OSC 52 escape sequence, which works over SSH. Used by default over SSH without a display"),
+        )
         .subcommand(
             App::new("init").about("Create a new password file").arg(
                 Arg::new("force-for-tests")
@@ -247,6 +538,19 @@ pub fn main_with_args(
                     .help("Forces initializing the file, used in integration tests only"),
             ),
         )
+        .subcommand(
+            App::new("agent")
+                .about("Keep your master password unlocked for a while, like ssh-agent")
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .takes_value(true)
+                        .value_name("seconds")
+                        .validator(validate_arg_digits)
+                        .help("Forget the master password after this many seconds without use (default: 900)"),
+                ),
+        )
+        .subcommand(App::new("lock").about("Make the agent forget your master password right away"))
         .subcommand(
             App::new("add")
                 .about("Add a new password manually")
@@ -265,11 +569,13 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
-                ),
+                )
+                .arg(selection_arg())
+                .args(details_args(false)),
         )
         .subcommand(
             App::new("change")
-                .about("Change a password manually")
+                .about("Change a password manually, or only its details if any are given")
                 .arg(
                     Arg::new("app")
                         .required(true)
@@ -280,7 +586,9 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
-                ),
+                )
+                .arg(selection_arg())
+                .args(details_args(true)),
         )
         .subcommand(
             App::new("delete").about("Delete a password").arg(
@@ -308,6 +616,7 @@ pub fn main_with_args(
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
                 )
+                .arg(selection_arg())
                 .arg(
                     Arg::new("alnum")
                         .short('a')
@@ -321,7 +630,8 @@ pub fn main_with_args(
                         .default_value("32")
                         .help("Set a custom length for the generated password")
                         .validator(validate_arg_digits),
-                ),
+                )
+                .args(generation_args()),
         )
         .subcommand(
             App::new("regenerate")
@@ -337,6 +647,7 @@ pub fn main_with_args(
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
                 )
+                .arg(selection_arg())
                 .arg(
                     Arg::new("alnum")
                         .short('a')
@@ -350,7 +661,8 @@ pub fn main_with_args(
                         .default_value("32")
                         .help("Set a custom length for the generated password")
                         .validator(validate_arg_digits),
-                ),
+                )
+                .args(generation_args()),
         )
         .subcommand(
             App::new("get")
@@ -365,8 +677,81 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .help("Show the password instead of copying it to the clipboard"),
+                )
+                .arg(selection_arg())
+                .arg(
+                    Arg::new("field")
+                        .long("field")
+                        .takes_value(true)
+                        .value_name("name")
+                        .help("Retrieve a custom field, the username, url or notes instead"),
+                )
+                .arg(
+                    Arg::new("sequence")
+                        .long("sequence")
+                        .conflicts_with_all(&["show", "field"])
+                        .help("Copy the username, then the password and the one-time code (if there is one) as you hit ENTER"),
                 ),
         )
+        .subcommand(
+            App::new("history")
+                .about("List the previous passwords of an app, or retrieve one of them")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("n")
+                        .help("The previous password to retrieve, 1 being the most recent")
+                        .validator(validate_arg_digits),
+                )
+                .arg(
+                    Arg::new("show")
+                        .short('s')
+                        .long("show")
+                        .help("Show the passwords instead of copying them to the clipboard"),
+                )
+                .arg(selection_arg()),
+        )
+        .subcommand(
+            App::new("rollback")
+                .about("Go back to a previous password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("n")
+                        .default_value("1")
+                        .help("The previous password to go back to, 1 being the most recent")
+                        .validator(validate_arg_digits),
+                )
+                .arg(
+                    Arg::new("show")
+                        .short('s')
+                        .long("show")
+                        .help("Show the password instead of copying it to the clipboard"),
+                )
+                .arg(selection_arg()),
+        )
+        .subcommand(
+            App::new("otp")
+                .about("Retrieve a two factor authentication code")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .help("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("show")
+                        .short('s')
+                        .long("show")
+                        .help("Show the code instead of copying it to the clipboard"),
+                )
+                .arg(selection_arg()),
+        )
         .subcommand(
             App::new("rename")
                 .about("Rename the app for a password")
@@ -395,13 +780,58 @@ pub fn main_with_args(
                         .help("Your new username for this account"),
                 ),
         )
-        .subcommand(App::new("list").about("List all apps and usernames"))
+        .subcommand(
+            App::new("list")
+                .about("List apps and usernames")
+                .arg(
+                    Arg::new("query")
+                        .help("Only list apps that match this, ie: \"fcbk\" for Facebook"),
+                )
+                .arg(
+                    Arg::new("username")
+                        .long("username")
+                        .takes_value(true)
+                        .help("Only list usernames that contain this"),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .long("tag")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .help("Only list passwords with this tag, can be repeated"),
+                )
+                .arg(
+                    Arg::new("older-than")
+                        .long("older-than")
+                        .takes_value(true)
+                        .value_name("age")
+                        .validator(list::parse_age)
+                        .help("Only list passwords not changed for this long, ie: 90d, 6m or 1y"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .takes_value(true)
+                        .possible_values(["name", "created", "updated"])
+                        .help("Sort by name, or oldest first by creation or update date (default: best matches first, or by name)"),
+                )
+                .arg(
+                    Arg::new("column")
+                        .long("column")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .use_delimiter(true)
+                        .possible_values(["created", "updated"])
+                        .help("Also show the creation or update date, can be repeated"),
+                ),
+        )
         .subcommand(
             App::new("import")
                 .setting(AppSettings::SubcommandRequiredElseHelp)
                 .about("Import all your existing passwords from elsewhere")
                 .subcommand(
                     App::new("json")
+                        .args(import_args())
                         .about("Import a file generated with `rooster export json`")
                         .arg(
                             Arg::new("path")
@@ -411,21 +841,107 @@ pub fn main_with_args(
                 )
                 .subcommand(
                     App::new("csv")
-                        .about("Import a file generated with `rooster export csv`")
+                        .args(import_args())
+                        .about("Import a file generated with `rooster export csv`, or any CSV file with --map")
                         .arg(
                             Arg::new("path")
                                 .required(true)
                                 .help("The path to the file you want to import"),
+                        )
+                        .arg(
+                            Arg::new("map")
+                                .long("map")
+                                .takes_value(true)
+                                .validator(importers::csv_map::validate_map)
+                                .help("Read the columns by their header, ie: name=Title,username=Login,password=Password (also: url, notes, tags, otp)"),
                         ),
                 )
                 .subcommand(
                     App::new("1password")
+                        .args(import_args())
                         .about("Import a \"Common Fields\" CSV export from 1Password")
                         .arg(
                             Arg::new("path")
                                 .required(true)
                                 .help("The path to the file you want to import"),
                         ),
+                )
+                .subcommand(
+                    App::new("rooster-bundle")
+                        .args(import_args())
+                        .about("Import a bundle generated with `rooster export rooster-bundle`")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    App::new("bitwarden")
+                        .args(import_args())
+                        .about("Import an unencrypted JSON export from Bitwarden")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    App::new("keepass")
+                        .args(import_args())
+                        .about("Import a \"KeePass XML (2.x)\" export from KeePass or KeePassXC")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    App::new("lastpass")
+                        .args(import_args())
+                        .about("Import a CSV export from LastPass")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    App::new("chrome")
+                        .args(import_args())
+                        .about("Import a CSV export from Chrome, Chromium, Edge or Brave")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    App::new("firefox")
+                        .args(import_args())
+                        .about("Import a CSV export from Firefox")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The path to the file you want to import"),
+                        ),
+                )
+                .subcommand(
+                    App::new("pass")
+                        .args(import_args())
+                        .about("Import the passwords of pass, the standard unix password manager")
+                        .arg(
+                            Arg::new("path")
+                                .required(true)
+                                .help("The password store directory, ie: ~/.password-store"),
+                        )
+                        .arg(
+                            Arg::new("decrypt-command")
+                                .long("decrypt-command")
+                                .takes_value(true)
+                                .default_value(importers::pass::DEFAULT_DECRYPT_COMMAND)
+                                .help("The command that decrypts a file, given its path as the last argument"),
+                        ),
                 ),
         )
         .subcommand(
@@ -437,9 +953,34 @@ pub fn main_with_args(
                 .subcommand(
                     App::new("1password")
                         .about("Export raw password data in 1Password compatible CSV format"),
+                )
+                .subcommand(
+                    App::new("rooster-bundle")
+                        .about("Export some passwords, encrypted with a password to share with someone else")
+                        .arg(
+                            Arg::new("query")
+                                .help("Only export passwords whose name matches this"),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .long("tag")
+                                .takes_value(true)
+                                .multiple_occurrences(true)
+                                .help("Only export passwords with this tag, can be repeated"),
+                        )
+                        .arg(
+                            Arg::new("recipient-password")
+                                .long("recipient-password")
+                                .required(true)
+                                .help("Ask for the password that the recipient will open the bundle with"),
+                        ),
                 ),
         )
         .subcommand(App::new("set-master-password").about("Set your master password"))
+        .subcommand(
+            App::new("restore-backup")
+                .about("Restore the password file from one of its automatic backups"),
+        )
         .subcommand(
             App::new("set-scrypt-params")
                 .about("Set the key derivation parameters")
@@ -468,14 +1009,96 @@ pub fn main_with_args(
                         .help("Disable parameter checks"),
                 ),
         )
+        .subcommand(
+            App::new("set-kdf-params")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .about("Switch to another key derivation function")
+                .subcommand(
+                    App::new("argon2id")
+                        .about("Use argon2id for key derivation")
+                        .arg(
+                            Arg::new("memory")
+                                .required(true)
+                                .help("The memory parameter, in KiB")
+                                .validator(validate_arg_digits),
+                        )
+                        .arg(
+                            Arg::new("iterations")
+                                .required(true)
+                                .help("The iterations parameter")
+                                .validator(validate_arg_digits),
+                        )
+                        .arg(
+                            Arg::new("parallelism")
+                                .required(true)
+                                .help("The parallelism parameter")
+                                .validator(validate_arg_digits),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .short('f')
+                                .long("force")
+                                .help("Disable parameter checks"),
+                        ),
+                ),
+        )
         .get_matches_from(args);
 
     let subcommand = matches.subcommand_name().unwrap();
 
     let command_matches = matches.subcommand_matches(subcommand).unwrap();
 
-    if subcommand == "init" {
-        match commands::init::callback_exec(command_matches, io, rooster_file_path) {
+    match output::Format::from_matches(command_matches) {
+        output::Format::Text => run(subcommand, command_matches, io, rooster_file_path),
+        output::Format::Json => {
+            let mut json_io = output::JsonOutput::new(io);
+            let code = run(subcommand, command_matches, &mut json_io, rooster_file_path);
+            json_io.finish(code)
+        }
+    }
+}
+
+fn run(
+    subcommand: &str,
+    command_matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> i32 {
+    // The callback for each command, and whether it changes the password store. Commands that
+    // don't are never written back to disk.
+    let command: Option<(CommandCallback<_>, bool)> = match subcommand {
+        // These work on the password file directly, see below.
+        "init" | "restore-backup" | "agent" | "lock" => None,
+        "get" => Some((commands::get::callback_exec, false)),
+        "add" => Some((commands::add::callback_exec, true)),
+        "delete" => Some((commands::delete::callback_exec, true)),
+        "generate" => Some((commands::generate::callback_exec, true)),
+        "regenerate" => Some((commands::regenerate::callback_exec, true)),
+        "list" => Some((commands::list::callback_exec, false)),
+        "import" => Some((commands::import::callback_exec, true)),
+        "export" => Some((commands::export::callback_exec, false)),
+        "set-master-password" => Some((commands::set_master_password::callback_exec, true)),
+        "set-scrypt-params" => Some((commands::set_scrypt_params::callback_exec, true)),
+        "set-kdf-params" => Some((commands::set_kdf_params::callback_exec, true)),
+        "history" => Some((commands::history::callback_exec, false)),
+        "rollback" => Some((commands::rollback::callback_exec, true)),
+        "otp" => Some((commands::otp::callback_exec, false)),
+        "rename" => Some((commands::rename::callback_exec, true)),
+        "transfer" => Some((commands::transfer::callback_exec, true)),
+        "change" => Some((commands::change::callback_exec, true)),
+        _ => unreachable!("Validation should have been done by `clap` before"),
+    };
+
+    // The agent runs for a long time, it must not keep the password file for itself.
+    if subcommand == "agent" {
+        match commands::agent::callback_exec(command_matches, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "lock" {
+        match commands::lock::callback_exec(command_matches, io) {
             Err(i) => return i,
             _ => return 0,
         }
@@ -483,7 +1106,12 @@ pub fn main_with_args(
 
     let password_file_path_as_string = rooster_file_path.to_string_lossy().into_owned();
 
-    if !rooster_file_path.exists() {
+    // Only `init` creates a password file, and restoring a backup brings a deleted one back.
+    // Other commands don't leave a lock file behind when there is no password file.
+    let creates_file = subcommand == "init"
+        || (subcommand == "restore-backup"
+            && safe_file::backup_path(rooster_file_path, 1).exists());
+    if !creates_file && !rooster_file_path.exists() {
         io.title("First time user", OutputType::Standard);
         io.nl(OutputType::Standard);
         io.info("Try `rooster init`.", OutputType::Standard);
@@ -501,6 +1129,34 @@ pub fn main_with_args(
         return 1;
     }
 
+    // Commands that only read the password file can run side by side, the others need it
+    // for themselves during the whole read-modify-write cycle. So do read-only commands that
+    // upgrade the file format.
+    let lock_mode = match command {
+        Some((_, false)) if !needs_upgrade(rooster_file_path) => lock::LockMode::Shared,
+        _ => lock::LockMode::Exclusive,
+    };
+    let wait = command_matches.is_present("wait");
+    let _lock = match lock_password_file(rooster_file_path, lock_mode, wait, io) {
+        Ok(lock) => lock,
+        Err(code) => return code,
+    };
+
+    if subcommand == "init" {
+        match commands::init::callback_exec(command_matches, io, rooster_file_path) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    // Restoring a backup must work even if the password file can't be opened anymore.
+    if subcommand == "restore-backup" {
+        match commands::restore_backup::callback_exec(command_matches, io, rooster_file_path) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
     let mut file = match open_password_file(password_file_path_as_string.deref()) {
         Ok(file) => file,
         Err(err) => {
@@ -525,34 +1181,44 @@ pub fn main_with_args(
         }
     };
 
-    let mut store = match get_password_store(&mut file, io) {
+    // Changing the master password or the key derivation params needs the master password,
+    // the agent doesn't know it.
+    let use_agent = !matches!(
+        subcommand,
+        "set-master-password" | "set-scrypt-params" | "set-kdf-params"
+    );
+    let source = master_password::Source::from_matches(command_matches);
+    let mut store = match get_password_store(&mut file, &source, use_agent, io) {
         Err(code) => return code,
         Ok(store) => store,
     };
 
-    let callback = match subcommand {
-        "get" => commands::get::callback_exec,
-        "add" => commands::add::callback_exec,
-        "delete" => commands::delete::callback_exec,
-        "generate" => commands::generate::callback_exec,
-        "regenerate" => commands::regenerate::callback_exec,
-        "list" => commands::list::callback_exec,
-        "import" => commands::import::callback_exec,
-        "export" => commands::export::callback_exec,
-        "set-master-password" => commands::set_master_password::callback_exec,
-        "set-scrypt-params" => commands::set_scrypt_params::callback_exec,
-        "rename" => commands::rename::callback_exec,
-        "transfer" => commands::transfer::callback_exec,
-        "change" => commands::change::callback_exec,
-        _ => unreachable!("Validation should have been done by `clap` before"),
-    };
+    let (callback, _) = command.unwrap();
 
     if let Err(code) = callback(command_matches, &mut store, io) {
         return code;
     }
 
-    if let Err(code) = sync_password_store(&mut store, &mut file, io) {
-        return code;
+    // Only save the password file when something changed. Read-only commands may still need to,
+    // ie: right after the file format was upgraded.
+    if store.is_dirty() {
+        // Another Rooster may be reading the password file right now.
+        if lock_mode == lock::LockMode::Shared {
+            io.error(
+                "Woops, I can't save your password file without locking it first.",
+                OutputType::Error,
+            );
+            return 1;
+        }
+
+        if let Err(code) = sync_password_store(&mut store, rooster_file_path, io) {
+            return code;
+        }
+
+        // The key may have changed, the agent can't open the password file with the old one.
+        if !use_agent {
+            add_key_to_agent(&store);
+        }
     }
 
     return 0;
@@ -1,5 +1,10 @@
-use crate::password::v2::{Password, PasswordStore};
+use crate::ffi;
+use crate::fuzzy;
+use crate::output;
+use crate::password::v3::{Password, PasswordStore};
+use crate::password::PasswordError;
 use crate::rclio::{CliInputOutput, OutputType};
+use std::time::SystemTime;
 
 /// Used to indicate lists should have a number, ie: 23 Google my.account@gmail.com
 pub const WITH_NUMBERS: bool = true;
@@ -7,6 +12,161 @@ pub const WITH_NUMBERS: bool = true;
 /// Used to indicate lists should not have a number, ie: Google my.account@gmail.com
 pub const WITHOUT_NUMBERS: bool = false;
 
+/// Describes how long ago something happened, ie: "3 hours ago"
+pub fn format_age(then: SystemTime, now: SystemTime) -> String {
+    let seconds = now.duration_since(then).map(|d| d.as_secs()).unwrap_or(0);
+
+    if seconds < 60 {
+        return "just now".to_owned();
+    }
+
+    let (value, unit) = if seconds < 60 * 60 {
+        (seconds / 60, "minute")
+    } else if seconds < 60 * 60 * 24 {
+        (seconds / (60 * 60), "hour")
+    } else {
+        (seconds / (60 * 60 * 24), "day")
+    };
+
+    format!("{} {}{} ago", value, unit, if value > 1 { "s" } else { "" })
+}
+
+/// Parses an age such as "90d", in hours (h), days (d), weeks (w), months (m) or years (y)
+///
+/// A number alone is a number of days. Months have 30 days and years 365.
+pub fn parse_age(age: &str) -> Result<u64, String> {
+    let age = age.trim();
+    let (number, unit) = match age.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => age.split_at(i),
+        None => (age, "d"),
+    };
+    let seconds_per_unit = match unit {
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "m" => 30 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown unit \"{}\", use h, d, w, m or y, ie: 90d",
+                unit
+            ))
+        }
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(seconds_per_unit))
+        .ok_or_else(|| format!("\"{}\" is not a valid age, ie: 90d", age))
+}
+
+/// Formats a date as YYYY-MM-DD, in the local time zone
+pub fn format_date(t: ffi::time_t) -> String {
+    let t = t as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+        return "?".to_owned();
+    }
+    format!(
+        "{:04}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday
+    )
+}
+
+/// How to sort the output of `rooster list`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    Name,
+    /// Oldest first
+    Created,
+    /// Least recently updated first
+    Updated,
+}
+
+/// Optional columns of `rooster list`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Created,
+    Updated,
+}
+
+/// Which passwords `rooster list` shows, and in which order
+pub struct Filter {
+    pub query: Option<String>,
+    pub username: Option<String>,
+    pub tags: Vec<String>,
+    /// Only passwords that were not updated for this many seconds
+    pub older_than: Option<u64>,
+    /// By default, the best matches of the query come first, or everything is sorted by name
+    pub sort: Option<Sort>,
+}
+
+impl Filter {
+    pub fn from_matches(matches: &clap::ArgMatches) -> Filter {
+        Filter {
+            query: matches.value_of("query").map(|q| q.to_owned()),
+            username: matches.value_of("username").map(|u| u.to_lowercase()),
+            tags: matches
+                .values_of("tag")
+                .map(|tags| tags.map(|t| t.to_lowercase()).collect())
+                .unwrap_or_default(),
+            // Validated by `clap` before.
+            older_than: matches
+                .value_of("older-than")
+                .and_then(|age| parse_age(age).ok()),
+            sort: match matches.value_of("sort") {
+                Some("name") => Some(Sort::Name),
+                Some("created") => Some(Sort::Created),
+                Some("updated") => Some(Sort::Updated),
+                _ => None,
+            },
+        }
+    }
+
+    fn accepts(&self, password: &Password, now: ffi::time_t) -> bool {
+        if let Some(username) = &self.username {
+            if !password.username.to_lowercase().contains(username.as_str()) {
+                return false;
+            }
+        }
+
+        let has_tag = |tag: &String| password.tags.iter().any(|t| t.to_lowercase() == *tag);
+        if !self.tags.iter().all(has_tag) {
+            return false;
+        }
+
+        match self.older_than {
+            Some(age) => (now.saturating_sub(password.updated_at) as u64) >= age,
+            None => true,
+        }
+    }
+
+    /// The passwords to list, sorted
+    pub fn apply<'a>(&self, store: &'a PasswordStore, now: ffi::time_t) -> Vec<&'a Password> {
+        let passwords = match &self.query {
+            Some(query) => store.search_passwords(query),
+            None => store.get_all_passwords(),
+        };
+
+        let mut passwords: Vec<&Password> = passwords
+            .into_iter()
+            .filter(|p| self.accepts(p, now))
+            .collect();
+
+        // Sorting is stable, so ties stay in the order of the search.
+        match self.sort {
+            None => {}
+            Some(Sort::Name) => passwords.sort_by_key(|p| p.name.to_lowercase()),
+            Some(Sort::Created) => passwords.sort_by_key(|p| p.created_at),
+            Some(Sort::Updated) => passwords.sort_by_key(|p| p.updated_at),
+        }
+
+        passwords
+    }
+}
+
 fn get_list_of_passwords(passwords: &Vec<&Password>, with_numbers: bool) -> Vec<String> {
     // Find the app name column length
     let longest_app_name = passwords.iter().fold(0, |acc, p| {
@@ -69,6 +229,27 @@ pub fn print_list_of_passwords(
     }
 }
 
+/// Prints passwords without numbers, followed by the optional columns
+pub fn print_table_of_passwords(
+    passwords: &Vec<&Password>,
+    columns: &[Column],
+    io: &mut impl CliInputOutput,
+) {
+    let list = get_list_of_passwords(passwords, WITHOUT_NUMBERS);
+
+    for (s, p) in list.into_iter().zip(passwords.iter()) {
+        let mut line = s;
+        for column in columns {
+            line.push(' ');
+            line.push_str(&format_date(match column {
+                Column::Created => p.created_at,
+                Column::Updated => p.updated_at,
+            }));
+        }
+        io.info(line, OutputType::Standard);
+    }
+}
+
 fn request_password_index_from_stdin(
     passwords: &Vec<&Password>,
     prompt: &str,
@@ -141,6 +322,10 @@ fn choose_password_in_list(
     request_password_index_from_stdin(passwords, prompt, io)
 }
 
+/// Finds the password the user means, asking which one unless the name matches exactly
+///
+/// Used by commands that change the password store, so that a typo can't delete or overwrite
+/// the wrong password.
 pub fn search_and_choose_password<'a>(
     store: &'a PasswordStore,
     query: &str,
@@ -148,8 +333,34 @@ pub fn search_and_choose_password<'a>(
     prompt: &str,
     io: &mut impl CliInputOutput,
 ) -> Option<&'a Password> {
-    let passwords = store.search_passwords(query);
-    if passwords.len() == 0 {
+    search_and_choose(store, query, false, with_numbers, prompt, io)
+}
+
+/// Like `search_and_choose_password`, but also picks a password that matches much better than
+/// the others without asking
+///
+/// Only for commands that read a password, ie: `get`, where picking the wrong one is harmless.
+pub fn search_and_pick_password<'a>(
+    store: &'a PasswordStore,
+    query: &str,
+    with_numbers: bool,
+    prompt: &str,
+    io: &mut impl CliInputOutput,
+) -> Option<&'a Password> {
+    search_and_choose(store, query, true, with_numbers, prompt, io)
+}
+
+fn search_and_choose<'a>(
+    store: &'a PasswordStore,
+    query: &str,
+    pick_clear_winner: bool,
+    with_numbers: bool,
+    prompt: &str,
+    io: &mut impl CliInputOutput,
+) -> Option<&'a Password> {
+    let ranked = store.rank_passwords(query);
+    if ranked.is_empty() {
+        output::remember_error(&PasswordError::NoSuchAppError);
         io.error(
             format!("Woops, I can't find any passwords for \"{}\".", query),
             OutputType::Error,
@@ -157,23 +368,31 @@ pub fn search_and_choose_password<'a>(
         return None;
     }
 
-    if let Some(&password) = passwords
+    if let Some(&(_, password)) = ranked
         .iter()
-        .find(|p| p.name.to_lowercase() == query.to_lowercase())
+        .find(|(_, p)| p.name.to_lowercase() == query.to_lowercase())
     {
-        return Some(&password);
+        return Some(password);
+    }
+
+    // No need to ask when one password matches much better than the others.
+    let scores: Vec<i32> = ranked.iter().map(|(score, _)| *score).collect();
+    if pick_clear_winner && fuzzy::is_clear_winner(query, &scores) {
+        return Some(ranked[0].1);
     }
 
+    let passwords: Vec<&Password> = ranked.into_iter().map(|(_, p)| p).collect();
     let index = choose_password_in_list(&passwords, with_numbers, prompt, io);
     Some(passwords[index])
 }
 
 #[cfg(test)]
 mod test {
-    use super::get_list_of_passwords;
+    use super::{format_age, get_list_of_passwords, parse_age, Filter, Sort};
     use crate::list::{WITHOUT_NUMBERS, WITH_NUMBERS};
-    use crate::password::v2::Password;
+    use crate::password::v3::{Password, PasswordStore};
     use crate::rutil::safe_string::SafeString;
+    use std::time::{Duration, SystemTime};
 
     // Creates a list of at least two passwords, and more if specified
     fn get_passwords(mut additional: i32) -> Vec<Password> {
@@ -248,4 +467,105 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90d"), Ok(90 * 24 * 60 * 60));
+        assert_eq!(parse_age("90"), Ok(90 * 24 * 60 * 60));
+        assert_eq!(parse_age("12h"), Ok(12 * 60 * 60));
+        assert_eq!(parse_age("2w"), Ok(14 * 24 * 60 * 60));
+        assert_eq!(parse_age("6m"), Ok(180 * 24 * 60 * 60));
+        assert_eq!(parse_age("1y"), Ok(365 * 24 * 60 * 60));
+        assert!(parse_age("").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("90s").is_err());
+        assert!(parse_age("-1d").is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut store = PasswordStore::new(SafeString::from_string("xxxx".to_owned())).unwrap();
+        let day = 24 * 60 * 60;
+        let now = 1000 * day;
+        for (name, username, tags, created, updated) in [
+            ("Youtube", "yt@example.com", vec!["video"], 10, 900),
+            ("Google", "me@gmail.com", vec!["mail", "work"], 20, 800),
+            ("Gitlab", "me@example.com", vec!["Work"], 5, 990),
+        ] {
+            let mut password = Password::new(name, username, "xxxx");
+            password.tags = tags.into_iter().map(|t| t.to_owned()).collect();
+            password.created_at = created * day;
+            password.updated_at = updated * day;
+            store.add_password(password).unwrap();
+        }
+
+        let filter = |query: Option<&str>,
+                      username: Option<&str>,
+                      tags: &[&str],
+                      older_than: Option<u64>,
+                      sort: Option<Sort>| {
+            Filter {
+                query: query.map(|q| q.to_owned()),
+                username: username.map(|u| u.to_owned()),
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                older_than,
+                sort,
+            }
+            .apply(&store, now)
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            filter(None, None, &[], None, None),
+            ["Gitlab", "Google", "Youtube"]
+        );
+        assert_eq!(
+            filter(Some("g"), None, &[], None, None),
+            ["Gitlab", "Google"]
+        );
+        assert_eq!(
+            filter(None, Some("example"), &[], None, None),
+            ["Gitlab", "Youtube"]
+        );
+        assert_eq!(
+            filter(None, None, &["work"], None, None),
+            ["Gitlab", "Google"]
+        );
+        assert_eq!(
+            filter(None, None, &["work", "mail"], None, None),
+            ["Google"]
+        );
+        assert_eq!(
+            filter(None, None, &[], Some(90 * day as u64), None),
+            ["Google", "Youtube"]
+        );
+        assert_eq!(
+            filter(None, None, &[], None, Some(Sort::Created)),
+            ["Gitlab", "Youtube", "Google"]
+        );
+        assert_eq!(
+            filter(None, None, &[], None, Some(Sort::Updated)),
+            ["Google", "Youtube", "Gitlab"]
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        let now = SystemTime::now();
+        assert_eq!(format_age(now, now), "just now");
+        assert_eq!(
+            format_age(now - Duration::from_secs(60), now),
+            "1 minute ago"
+        );
+        assert_eq!(
+            format_age(now - Duration::from_secs(3 * 60 * 60), now),
+            "3 hours ago"
+        );
+        assert_eq!(
+            format_age(now - Duration::from_secs(2 * 24 * 60 * 60), now),
+            "2 days ago"
+        );
+    }
 }
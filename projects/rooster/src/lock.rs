@@ -0,0 +1,147 @@
+use std::fs::File;
+use std::io::Result as IoResult;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// For commands that only read the password file, several of them can run at once
+    Shared,
+    /// For commands that change the password file, nothing else can run at the same time
+    Exclusive,
+}
+
+/// Advisory lock on the password file, released when dropped
+///
+/// The password file itself is replaced on every save, so the lock is taken on a separate
+/// lock file that stays in place, ie: `~/.passwords.rooster.lock`.
+///
+/// Only Unix has locks, elsewhere nothing stops two Rooster commands from running together.
+pub struct Lock {
+    // Closing the file releases the lock.
+    _file: File,
+}
+
+/// Path of the lock file for a password file
+///
+/// Symlinks are resolved, so that the password file has the same lock whichever way it's opened.
+pub fn lock_path(path: &Path) -> PathBuf {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut lock = path.as_os_str().to_os_string();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+/// Locks the password file
+///
+/// If `wait` is false and the lock is held by another process, this fails right away with an
+/// error of kind `WouldBlock`.
+pub fn lock(path: &Path, mode: LockMode, wait: bool) -> IoResult<Lock> {
+    let file = open_lock_file(&lock_path(path))?;
+    flock(&file, mode, wait)?;
+    Ok(Lock { _file: file })
+}
+
+#[cfg(unix)]
+fn open_lock_file(path: &Path) -> IoResult<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn open_lock_file(path: &Path) -> IoResult<File> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+#[cfg(unix)]
+fn flock(file: &File, mode: LockMode, wait: bool) -> IoResult<()> {
+    use std::io::Error as IoError;
+    use std::os::unix::io::AsRawFd;
+
+    let mut operation = match mode {
+        LockMode::Shared => libc::LOCK_SH,
+        LockMode::Exclusive => libc::LOCK_EX,
+    };
+    if !wait {
+        operation |= libc::LOCK_NB;
+    }
+
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+
+        let err = IoError::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn flock(_file: &File, _mode: LockMode, _wait: bool) -> IoResult<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::{lock, LockMode};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_shared_locks_can_be_held_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.rooster");
+
+        let _first = lock(&path, LockMode::Shared, false).unwrap();
+        let _second = lock(&path, LockMode::Shared, false).unwrap();
+        assert_eq!(
+            lock(&path, LockMode::Exclusive, false)
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::WouldBlock
+        );
+    }
+
+    #[test]
+    fn test_exclusive_lock_is_released_when_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.rooster");
+
+        let exclusive = lock(&path, LockMode::Exclusive, false).unwrap();
+        assert_eq!(
+            lock(&path, LockMode::Shared, false).err().unwrap().kind(),
+            ErrorKind::WouldBlock
+        );
+        drop(exclusive);
+        assert!(lock(&path, LockMode::Shared, false).is_ok());
+    }
+
+    #[test]
+    fn test_symlinks_share_the_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.rooster");
+        let link = dir.path().join("link.rooster");
+        std::fs::write(&path, b"").unwrap();
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+
+        let _exclusive = lock(&link, LockMode::Exclusive, false).unwrap();
+        assert_eq!(
+            lock(&path, LockMode::Shared, false).err().unwrap().kind(),
+            ErrorKind::WouldBlock
+        );
+        assert!(!dir.path().join("link.rooster.lock").exists());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::rclio::CliInputOutput;
+use crate::rutil::safe_string::SafeString;
+use crate::rutil::safe_vec::SafeVec;
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult};
+use std::path::PathBuf;
+
+/// Where the master password comes from
+///
+/// Only `Prompt` is interactive. The others let Rooster run from scripts, CI jobs or cron.
+pub enum Source {
+    /// Ask on the TTY
+    Prompt,
+    /// Read from an open file descriptor, set with `--password-fd`
+    Fd(i32),
+    /// Read from a file, set with `--password-file`
+    File(PathBuf),
+    /// Read from the output of a command, set with `ROOSTER_MASTER_PASSWORD_COMMAND`
+    Command(String),
+}
+
+impl Source {
+    pub fn from_matches(matches: &clap::ArgMatches) -> Source {
+        if let Some(fd) = matches
+            .value_of("password-fd")
+            .and_then(|fd| fd.parse::<i32>().ok())
+        {
+            return Source::Fd(fd);
+        }
+
+        if let Some(path) = matches.value_of("password-file") {
+            return Source::File(PathBuf::from(path));
+        }
+
+        match std::env::var("ROOSTER_MASTER_PASSWORD_COMMAND") {
+            Ok(command) if !command.trim().is_empty() => Source::Command(command),
+            _ => Source::Prompt,
+        }
+    }
+
+    /// Whether a wrong password can be typed again
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Source::Prompt)
+    }
+
+    pub fn read(&self, io: &mut impl CliInputOutput) -> IoResult<SafeString> {
+        let mut input = SafeVec::new(Vec::new());
+
+        match self {
+            Source::Prompt => return io.prompt_password("Type your master password: "),
+            Source::Fd(fd) => read_fd(*fd, &mut input)?,
+            Source::File(path) => {
+                std::fs::File::open(path)?.read_to_end(input.inner_mut())?;
+            }
+            Source::Command(command) => run_command(command, &mut input)?,
+        }
+
+        first_line(&input)
+    }
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32, input: &mut SafeVec) -> IoResult<()> {
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+
+    // The standard input and outputs are for talking to the user.
+    if fd <= 2 {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            "the file descriptor must be 3 or more",
+        ));
+    }
+
+    // The file descriptor may be one that Rooster uses too, so it's never closed here.
+    let mut file = ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+    file.read_to_end(input.inner_mut())?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32, _input: &mut SafeVec) -> IoResult<()> {
+    Err(IoError::new(
+        ErrorKind::Unsupported,
+        "file descriptors are only supported on Unix",
+    ))
+}
+
+/// Runs a command such as `pass show rooster`, split into words like a shell would
+///
+/// The command can still ask things on the TTY, ie: to touch a hardware token.
+fn run_command(command: &str, input: &mut SafeVec) -> IoResult<()> {
+    use std::process::{Command, Stdio};
+
+    let words = split_words(command)?;
+    let (program, args) = words
+        .split_first()
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "the command is empty"))?;
+
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    *input = SafeVec::new(output.stdout);
+
+    if !output.status.success() {
+        return Err(IoError::other(format!(
+            "`{}` failed with {}",
+            command, output.status
+        )));
+    }
+    Ok(())
+}
+
+/// Splits a command into words, ie: `secret-tool lookup service "rooster vault"` has 4 words
+///
+/// Quotes and backslashes work like in a POSIX shell. Nothing is expanded, variables and globs
+/// are passed as is.
+pub fn split_words(command: &str) -> IoResult<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("'")),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated("\"")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("\"")),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(unterminated("\\")),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+fn unterminated(quote: &str) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidInput,
+        format!("the command has an unterminated {}", quote),
+    )
+}
+
+/// The master password is the first line, like with `pass` which keeps other data below it
+fn first_line(input: &SafeVec) -> IoResult<SafeString> {
+    let line = input.split(|c| *c == b'\n').next().unwrap_or(&[]);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = std::str::from_utf8(line)
+        .map_err(|_| IoError::new(ErrorKind::InvalidData, "the password is not valid UTF-8"))?;
+    if line.is_empty() {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            "the password is empty",
+        ));
+    }
+    Ok(SafeString::from_string(line.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{first_line, split_words};
+    use crate::rutil::safe_vec::SafeVec;
+    use std::ops::Deref;
+
+    #[test]
+    fn test_first_line() {
+        let read = |s: &str| first_line(&SafeVec::new(s.as_bytes().to_vec()));
+        assert_eq!(read("xxxx").unwrap().deref(), "xxxx");
+        assert_eq!(read("xxxx\n").unwrap().deref(), "xxxx");
+        assert_eq!(read("xxxx\r\nurl: example.com\n").unwrap().deref(), "xxxx");
+        assert_eq!(read(" x x \n").unwrap().deref(), " x x ");
+        assert!(read("").is_err());
+        assert!(read("\nxxxx").is_err());
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(
+            split_words("pass  show\trooster ").unwrap(),
+            vec!["pass", "show", "rooster"]
+        );
+        assert_eq!(
+            split_words(r#"secret-tool lookup service "rooster vault""#).unwrap(),
+            vec!["secret-tool", "lookup", "service", "rooster vault"]
+        );
+        assert_eq!(
+            split_words(r#"cat '/a b/$HOME' my\ file "a \"b\" \c" '' x""y"#).unwrap(),
+            vec!["cat", "/a b/$HOME", "my file", r#"a "b" \c"#, "", "xy"]
+        );
+        assert!(split_words("").unwrap().is_empty());
+        assert!(split_words("cat 'file").is_err());
+        assert!(split_words("cat \"file").is_err());
+        assert!(split_words("cat file\\").is_err());
+    }
+}
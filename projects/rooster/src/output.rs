@@ -0,0 +1,156 @@
+use crate::password::PasswordError;
+use crate::rclio::{CliInputOutput, OutputType};
+use crate::rutil::safe_string::SafeString;
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::io::Result as IoResult;
+
+/// How commands report their results, set with `--output`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Sentences for humans
+    Text,
+    /// One JSON document on the standard output, for scripts
+    Json,
+}
+
+impl Format {
+    pub fn from_matches(matches: &clap::ArgMatches) -> Format {
+        match matches.value_of("output") {
+            Some("json") => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// Code of errors that don't come from the password store, ie: a file that can't be read
+const GENERIC_ERROR_CODE: &str = "error";
+
+thread_local! {
+    // Commands only return an exit code, so the reason they failed is kept on the side.
+    static ERROR_CODE: Cell<Option<&'static str>> = const { Cell::new(None) };
+
+    // Set while the JSON document is printed, the only thing `JsonOutput` lets through to stdout.
+    static PRINTING_JSON: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Remembers why the command failed, for the JSON output
+///
+/// The first error wins, as the next ones are usually consequences of it.
+pub fn remember_error(err: &PasswordError) {
+    ERROR_CODE.with(|code| {
+        if code.get().is_none() {
+            code.set(Some(err.code()));
+        }
+    });
+}
+
+fn take_error_code() -> Option<&'static str> {
+    ERROR_CODE.with(|code| code.take())
+}
+
+/// Prints the result of a command as a JSON document
+pub fn print_json(value: &Value, io: &mut impl CliInputOutput) {
+    PRINTING_JSON.with(|printing| printing.set(true));
+    io.writeln(value.to_string(), OutputType::Standard);
+    PRINTING_JSON.with(|printing| printing.set(false));
+}
+
+/// Where `JsonOutput` sends a write, everything but the JSON document goes to the standard error
+fn json_output_type() -> OutputType {
+    if PRINTING_JSON.with(|printing| printing.get()) {
+        OutputType::Standard
+    } else {
+        OutputType::Error
+    }
+}
+
+/// Input/output used with `--output json`
+///
+/// Everything but the JSON document goes to the standard error right away, including prompts.
+/// Errors are also gathered, and printed as a JSON document if the command fails.
+pub struct JsonOutput<'a, IO: CliInputOutput> {
+    inner: &'a mut IO,
+    errors: Vec<String>,
+}
+
+impl<'a, IO: CliInputOutput> JsonOutput<'a, IO> {
+    pub fn new(inner: &'a mut IO) -> JsonOutput<'a, IO> {
+        // Left over from an earlier command that ran in the same thread, ie: in tests.
+        take_error_code();
+        JsonOutput {
+            inner,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Prints the errors if the command failed, and returns its exit code
+    pub fn finish(self, exit_code: i32) -> i32 {
+        let code = take_error_code();
+        if exit_code != 0 {
+            let document = json!({
+                "error": {
+                    "code": code.unwrap_or(GENERIC_ERROR_CODE),
+                    "message": self.errors.join("\n"),
+                }
+            });
+            print_json(&document, self.inner);
+        }
+        exit_code
+    }
+}
+
+impl<'a, IO: CliInputOutput> CliInputOutput for JsonOutput<'a, IO> {
+    fn read_line(&mut self) -> IoResult<String> {
+        self.inner.read_line()
+    }
+
+    fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String> {
+        self.inner.prompt_line(prompt)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        self.inner.read_password()
+    }
+
+    fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString> {
+        self.inner.prompt_password(prompt)
+    }
+
+    fn nl(&mut self, _output_type: OutputType) {
+        self.inner.nl(OutputType::Error)
+    }
+
+    fn write(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.inner.write(s, json_output_type())
+    }
+
+    fn writeln(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.inner.writeln(s, json_output_type())
+    }
+
+    fn title(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.inner.title(s, OutputType::Error)
+    }
+
+    fn info(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.inner.info(s, OutputType::Error)
+    }
+
+    fn warning(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.inner.warning(s, OutputType::Error)
+    }
+
+    fn error(&mut self, s: impl ToString, _output_type: OutputType) {
+        let message = s.to_string();
+        self.inner.error(&message, OutputType::Error);
+        let message = message.trim();
+        if !message.is_empty() {
+            self.errors.push(message.to_owned());
+        }
+    }
+
+    fn success(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.inner.success(s, OutputType::Error)
+    }
+}
@@ -1,5 +1,6 @@
 pub mod v1;
 pub mod v2;
+pub mod v3;
 
 use crate::rutil::safe_string::SafeString;
 use crate::rutil::safe_vec::SafeVec;
@@ -19,29 +20,83 @@ pub enum PasswordError {
     CorruptionError,
     CorruptionLikelyError,
     NeedUpgradeErrorFromV1,
+    NeedUpgradeErrorFromV2,
     NoUpgradeError,
     EmptyPasswordError,
 }
 
+impl PasswordError {
+    /// Identifies the error in JSON output, these must never change
+    pub fn code(&self) -> &'static str {
+        match self {
+            PasswordError::DecryptionError => "decryption",
+            PasswordError::EncryptionError => "encryption",
+            PasswordError::NoSuchAppError => "no_such_app",
+            PasswordError::AppExistsError => "app_exists",
+            PasswordError::Io(_) => "io",
+            PasswordError::OutdatedRoosterBinaryError => "outdated_rooster_binary",
+            PasswordError::InvalidJsonError => "invalid_json",
+            PasswordError::CorruptionError => "corruption",
+            PasswordError::CorruptionLikelyError => "corruption_likely",
+            PasswordError::NeedUpgradeErrorFromV1 => "need_upgrade_from_v1",
+            PasswordError::NeedUpgradeErrorFromV2 => "need_upgrade_from_v2",
+            PasswordError::NoUpgradeError => "no_upgrade",
+            PasswordError::EmptyPasswordError => "empty_password",
+        }
+    }
+}
+
 impl From<IoError> for PasswordError {
     fn from(err: IoError) -> PasswordError {
         PasswordError::Io(err)
     }
 }
 
-fn upgrade_v1_v2(
+fn upgrade_v1_v3(
     v1_passwords: &[v1::Password],
-    v2_store: &mut v2::PasswordStore,
+    v3_store: &mut v3::PasswordStore,
 ) -> Result<(), PasswordError> {
     for p in v1_passwords.iter() {
-        let v2_password = v2::Password {
+        let v3_password = v3::Password {
             name: p.name.clone(),
             username: p.username.clone(),
             password: p.password.clone(),
             created_at: p.created_at,
             updated_at: p.updated_at,
+            url: None,
+            notes: None,
+            tags: Vec::new(),
+            fields: Vec::new(),
+            history: Vec::new(),
+            otp: None,
+            policy: None,
         };
-        v2_store.add_password(v2_password)?;
+        v3_store.add_password(v3_password)?;
+    }
+
+    Ok(())
+}
+
+fn upgrade_v2_v3(
+    v2_passwords: &[v2::Password],
+    v3_store: &mut v3::PasswordStore,
+) -> Result<(), PasswordError> {
+    for p in v2_passwords.iter() {
+        let v3_password = v3::Password {
+            name: p.name.clone(),
+            username: p.username.clone(),
+            password: p.password.clone(),
+            created_at: p.created_at,
+            updated_at: p.updated_at,
+            url: None,
+            notes: None,
+            tags: Vec::new(),
+            fields: Vec::new(),
+            history: Vec::new(),
+            otp: None,
+            policy: None,
+        };
+        v3_store.add_password(v3_password)?;
     }
 
     Ok(())
@@ -50,14 +105,34 @@ fn upgrade_v1_v2(
 pub fn upgrade(
     master_password: SafeString,
     input: SafeVec,
-) -> Result<v2::PasswordStore, PasswordError> {
-    // If we can't read v1 passwords, we have a hard error, because we previously tried
-    // to read the passwords as v2. Which failed. That means we can't upgrade.
-    let v1_passwords = v1::get_all_passwords(master_password.deref(), input.deref())?;
+) -> Result<v3::PasswordStore, PasswordError> {
+    match v2::get_all_passwords(master_password.deref(), input.deref()) {
+        // Upgrade from v2 to v3 if we could read v2 passwords, keeping the scrypt params the
+        // user may have chosen.
+        Ok(v2_contents) => {
+            let mut v3_store = v3::PasswordStore::with_kdf(
+                master_password.clone(),
+                v3::Kdf::Scrypt {
+                    log2_n: v2_contents.scrypt_log2_n,
+                    r: v2_contents.scrypt_r,
+                    p: v2_contents.scrypt_p,
+                },
+            )?;
+            upgrade_v2_v3(v2_contents.passwords.deref(), &mut v3_store)?;
 
-    // Upgrade from v1 to v2 if we could read v1 passwords.
-    let mut v2_store = v2::PasswordStore::new(master_password.clone())?;
-    upgrade_v1_v2(v1_passwords.deref(), &mut v2_store)?;
+            Ok(v3_store)
+        }
+        Err(PasswordError::NeedUpgradeErrorFromV1) => {
+            // If we can't read v1 passwords, we have a hard error, because we previously tried
+            // to read the passwords as v2. Which failed. That means we can't upgrade.
+            let v1_passwords = v1::get_all_passwords(master_password.deref(), input.deref())?;
 
-    Ok(v2_store)
+            // Upgrade from v1 to v3 if we could read v1 passwords.
+            let mut v3_store = v3::PasswordStore::new(master_password.clone())?;
+            upgrade_v1_v3(v1_passwords.deref(), &mut v3_store)?;
+
+            Ok(v3_store)
+        }
+        Err(err) => Err(err),
+    }
 }
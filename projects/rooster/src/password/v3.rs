@@ -0,0 +1,1516 @@
+use crate::ffi;
+use crate::fuzzy;
+use crate::generate::Policy;
+use crate::password::PasswordError;
+use crate::rutil::safe_string::SafeString;
+use crate::rutil::safe_vec::SafeVec;
+use crate::safe_file;
+use crate::totp::Otp;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Error;
+use std::io::{Cursor, Read, Result as IoResult, Write};
+use std::ops::Deref;
+use std::os::raw::{c_char, c_uchar, c_ulonglong};
+use std::path::Path;
+
+extern "C" {
+    pub fn crypto_pwhash_scryptsalsa208sha256_ll(
+        passwd: *const u8,
+        passwdlen: usize,
+        salt: *const u8,
+        saltlen: usize,
+        n: u64,
+        r: u32,
+        p: u32,
+        buf: *mut u8,
+        buflen: usize,
+    ) -> libc::c_int;
+
+    pub fn crypto_kdf_derive_from_key(
+        subkey: *mut libc::c_uchar,
+        subkey_len: usize,
+        subkey_id: u64,
+        ctx: *const libc::c_char,
+        key: *const libc::c_uchar,
+    ) -> libc::c_int;
+
+    pub fn crypto_auth_hmacsha512256(
+        out: *mut libc::c_uchar,
+        in_: *const libc::c_uchar,
+        inlen: libc::c_ulonglong,
+        k: *const libc::c_uchar,
+    ) -> libc::c_int;
+
+    pub fn crypto_auth_hmacsha512256_verify(
+        h: *const libc::c_uchar,
+        in_: *const libc::c_uchar,
+        inlen: libc::c_ulonglong,
+        k: *const libc::c_uchar,
+    ) -> libc::c_int;
+
+    pub fn crypto_aead_xchacha20poly1305_ietf_encrypt(
+        c: *mut libc::c_uchar,
+        clen_p: *mut libc::c_ulonglong,
+        m: *const libc::c_uchar,
+        mlen: libc::c_ulonglong,
+        ad: *const libc::c_uchar,
+        adlen: libc::c_ulonglong,
+        nsec: *const libc::c_uchar,
+        npub: *const libc::c_uchar,
+        k: *const libc::c_uchar,
+    ) -> libc::c_int;
+
+    pub fn crypto_aead_xchacha20poly1305_ietf_decrypt(
+        m: *mut libc::c_uchar,
+        mlen_p: *mut libc::c_ulonglong,
+        nsec: *mut libc::c_uchar,
+        c: *const libc::c_uchar,
+        clen: libc::c_ulonglong,
+        ad: *const libc::c_uchar,
+        adlen: libc::c_ulonglong,
+        npub: *const libc::c_uchar,
+        k: *const libc::c_uchar,
+    ) -> libc::c_int;
+}
+
+/// The nonce is 192 bits long.
+///
+/// XChaCha20-Poly1305 has a nonce that is large enough to be picked at random
+/// every time the file is saved without any risk of reusing it.
+const NONCE_LEN: usize = 24;
+
+/// Length of the key derived from the user password, in bytes
+const KEY_LEN: usize = 32;
+
+/// Length of the subkeys derived from the key, in bytes
+const SUBKEY_LEN: usize = 32;
+
+/// Length of the salt passed to the key derivation function, in bytes
+const SALT_LEN: usize = 32;
+
+/// Length of the key check, in bytes
+const KEY_CHECK_LEN: usize = 32;
+
+/// Length of the Poly1305 tag appended to the encrypted blob, in bytes
+const TAG_LEN: usize = 16;
+
+/// Context used to derive subkeys, must be exactly 8 bytes
+const SUBKEY_CONTEXT: &[u8; 8] = b"rooster3";
+
+/// Subkey used to encrypt and authenticate the password data
+const ENCRYPTION_SUBKEY_ID: u64 = 1;
+
+/// Subkey used to check the master password before decrypting anything
+const AUTHENTICATION_SUBKEY_ID: u64 = 2;
+
+/// Scrypt parameters
+const SCRYPT_PARAM_LOG2_N: u8 = 12;
+const SCRYPT_PARAM_R: u32 = 8;
+const SCRYPT_PARAM_P: u32 = 1;
+
+/// Identifiers of the key derivation functions in the file header
+const KDF_SCRYPT: u8 = 1;
+const KDF_ARGON2ID: u8 = 2;
+
+/// Key derivation function used to derive the key from the master password
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kdf {
+    Scrypt {
+        log2_n: u8,
+        r: u32,
+        p: u32,
+    },
+    /// Memory is in KiB
+    Argon2id {
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+/// The version of this lib
+pub const VERSION: u32 = 3;
+
+// Create a random nonce.
+fn generate_random_nonce() -> IoResult<[u8; NONCE_LEN]> {
+    let mut bytes: [u8; NONCE_LEN] = [0; NONCE_LEN];
+    let mut rng = OsRng;
+    rng.fill_bytes(&mut bytes);
+    Ok(bytes)
+}
+
+// Create a random salt.
+fn generate_random_salt() -> IoResult<[u8; SALT_LEN]> {
+    let mut bytes: [u8; SALT_LEN] = [0; SALT_LEN];
+    let mut rng = OsRng;
+    rng.fill_bytes(&mut bytes);
+    Ok(bytes)
+}
+
+/// Upper limits of the key derivation parameters
+///
+/// They are read from the file before anything can be authenticated, so a crafted file, ie: a
+/// bundle sent by someone else, must not be able to exhaust the memory or run forever.
+const MAX_KDF_MEMORY_KIB: u64 = 4 * 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 100;
+const MAX_KDF_PARALLELISM: u32 = 64;
+
+impl Kdf {
+    /// Checks that the parameters are acceptable, returning the reason if they aren't
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Kdf::Scrypt { log2_n, r, p } => {
+                if log2_n == 0 || r == 0 || p == 0 {
+                    return Err(format!(
+                        "the parameters must be > 0 ({}, {}, {})",
+                        log2_n, r, p
+                    ));
+                }
+                // Scrypt uses 128 * r * 2^log2_n bytes
+                let memory_kib = (r as u128) << log2_n.min(64) >> 3;
+                if log2_n >= 64 || memory_kib > MAX_KDF_MEMORY_KIB as u128 {
+                    return Err(format!(
+                        "the parameters would use more than {} GiB of memory",
+                        MAX_KDF_MEMORY_KIB / 1024 / 1024
+                    ));
+                }
+                if p > MAX_KDF_PARALLELISM {
+                    return Err(format!("p must be at most {}", MAX_KDF_PARALLELISM));
+                }
+            }
+            Kdf::Argon2id {
+                memory,
+                iterations,
+                parallelism,
+            } => {
+                if memory as u64 > MAX_KDF_MEMORY_KIB {
+                    return Err(format!(
+                        "the memory must be at most {} KiB",
+                        MAX_KDF_MEMORY_KIB
+                    ));
+                }
+                if iterations > MAX_KDF_ITERATIONS {
+                    return Err(format!(
+                        "the iterations must be at most {}",
+                        MAX_KDF_ITERATIONS
+                    ));
+                }
+                if parallelism > MAX_KDF_PARALLELISM {
+                    return Err(format!(
+                        "the parallelism must be at most {}",
+                        MAX_KDF_PARALLELISM
+                    ));
+                }
+                argon2::Params::new(memory, iterations, parallelism, Some(KEY_LEN))
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Derives a 256 bits encryption key from the password.
+///
+/// Parameters that are not acceptable mean the file was corrupted or crafted.
+fn generate_encryption_key(
+    master_password: &str,
+    salt: [u8; SALT_LEN],
+    kdf: Kdf,
+) -> Result<SafeVec, PasswordError> {
+    kdf.check().map_err(|_| PasswordError::CorruptionError)?;
+    let mut output = SafeVec::new(vec![0u8; KEY_LEN]);
+
+    match kdf {
+        Kdf::Scrypt { log2_n, r, p } => {
+            let result = unsafe {
+                crypto_pwhash_scryptsalsa208sha256_ll(
+                    master_password.as_ptr(),
+                    master_password.len(),
+                    salt.as_ptr(),
+                    salt.len(),
+                    1 << log2_n as u64,
+                    r,
+                    p,
+                    output.as_mut_ptr(),
+                    KEY_LEN,
+                )
+            };
+
+            if result != 0 {
+                return Err(PasswordError::Io(std::io::Error::other(format!(
+                    "deriving the scrypt key failed ({})",
+                    result
+                ))));
+            }
+        }
+        Kdf::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => {
+            let params = argon2::Params::new(memory, iterations, parallelism, Some(KEY_LEN))
+                .map_err(|_| PasswordError::CorruptionError)?;
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                .hash_password_into(master_password.as_bytes(), &salt, &mut output)
+                .map_err(|err| {
+                    PasswordError::Io(std::io::Error::other(format!(
+                        "deriving the argon2id key failed ({})",
+                        err
+                    )))
+                })?;
+        }
+    }
+
+    Ok(output)
+}
+
+/// Derives a 256 bits subkey from the key, so that the key itself is never used directly.
+fn generate_subkey(key: &[u8], subkey_id: u64) -> SafeVec {
+    let mut output = SafeVec::new(vec![0u8; SUBKEY_LEN]);
+
+    let result = unsafe {
+        crypto_kdf_derive_from_key(
+            output.as_mut_ptr(),
+            SUBKEY_LEN,
+            subkey_id,
+            SUBKEY_CONTEXT.as_ptr() as *const c_char,
+            key.as_ptr(),
+        )
+    };
+
+    if result != 0 {
+        panic!("Deriving subkey failed: {:?}", result);
+    }
+
+    output
+}
+
+/// Creates the header that is passed as associated data to the AEAD construction
+fn header(version: u32, kdf: Kdf, salt: &[u8], nonce: &[u8]) -> Result<Vec<u8>, PasswordError> {
+    let mut header: Vec<u8> = Vec::new();
+    header.write_u32::<BigEndian>(version)?;
+    match kdf {
+        Kdf::Scrypt { log2_n, r, p } => {
+            header.write_u8(KDF_SCRYPT)?;
+            header.write_u8(log2_n)?;
+            header.write_u32::<BigEndian>(r)?;
+            header.write_u32::<BigEndian>(p)?;
+        }
+        Kdf::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        } => {
+            header.write_u8(KDF_ARGON2ID)?;
+            header.write_u32::<BigEndian>(memory)?;
+            header.write_u32::<BigEndian>(iterations)?;
+            header.write_u32::<BigEndian>(parallelism)?;
+        }
+    }
+    header.write_all(salt)?;
+    header.write_all(nonce)?;
+    Ok(header)
+}
+
+/// Creates a HMAC of the header, which tells us whether the master password is right
+fn key_check(authentication_subkey: &[u8], header: &[u8]) -> Vec<u8> {
+    let mut key_check = vec![0u8; KEY_CHECK_LEN];
+
+    let result = unsafe {
+        crypto_auth_hmacsha512256(
+            key_check.as_mut_ptr() as *mut c_uchar,
+            header.as_ptr() as *const c_uchar,
+            header.len() as c_ulonglong,
+            authentication_subkey.as_ptr() as *const c_uchar,
+        )
+    };
+
+    if result != 0 {
+        panic!("Creating HMAC-SHA512256 key check failed: {:?}", result);
+    }
+
+    key_check
+}
+
+/// Encrypts and authenticates the data, and authenticates the header
+fn encrypt(
+    encryption_subkey: &[u8],
+    header: &[u8],
+    nonce: &[u8],
+    data: &[u8],
+) -> Result<Vec<u8>, PasswordError> {
+    let mut encrypted = vec![0u8; data.len() + TAG_LEN];
+    let mut encrypted_len: c_ulonglong = 0;
+
+    let result = unsafe {
+        crypto_aead_xchacha20poly1305_ietf_encrypt(
+            encrypted.as_mut_ptr() as *mut c_uchar,
+            &mut encrypted_len,
+            data.as_ptr() as *const c_uchar,
+            data.len() as c_ulonglong,
+            header.as_ptr() as *const c_uchar,
+            header.len() as c_ulonglong,
+            std::ptr::null(),
+            nonce.as_ptr() as *const c_uchar,
+            encryption_subkey.as_ptr() as *const c_uchar,
+        )
+    };
+
+    if result != 0 {
+        return Err(PasswordError::EncryptionError);
+    }
+
+    encrypted.truncate(encrypted_len as usize);
+    Ok(encrypted)
+}
+
+/// Decrypts the data, failing if either the data or the header has been tampered with
+fn decrypt(
+    encryption_subkey: &[u8],
+    header: &[u8],
+    nonce: &[u8],
+    encrypted: &[u8],
+) -> Result<SafeVec, PasswordError> {
+    if encrypted.len() < TAG_LEN {
+        return Err(PasswordError::CorruptionError);
+    }
+
+    let mut decrypted = SafeVec::new(vec![0u8; encrypted.len() - TAG_LEN]);
+    let mut decrypted_len: c_ulonglong = 0;
+
+    let result = unsafe {
+        crypto_aead_xchacha20poly1305_ietf_decrypt(
+            decrypted.as_mut_ptr() as *mut c_uchar,
+            &mut decrypted_len,
+            std::ptr::null_mut(),
+            encrypted.as_ptr() as *const c_uchar,
+            encrypted.len() as c_ulonglong,
+            header.as_ptr() as *const c_uchar,
+            header.len() as c_ulonglong,
+            nonce.as_ptr() as *const c_uchar,
+            encryption_subkey.as_ptr() as *const c_uchar,
+        )
+    };
+
+    if result != 0 {
+        return Err(PasswordError::CorruptionError);
+    }
+
+    decrypted.inner_mut().truncate(decrypted_len as usize);
+    Ok(decrypted)
+}
+
+/// The schema of the JSON content in the password file.
+///
+/// ```json
+/// {
+///     "passwords": [
+///         "name": "YouTube",
+///         "username": "conradk",
+///         "password": "xxxxxxxx",
+///         "created_at": 23145436,
+///         "updated_at": 23145546,
+///         "url": "https://youtube.com",
+///         "notes": "Recovery codes are in the drawer",
+///         "tags": ["video", "google"],
+///         "fields": [
+///             { "name": "PIN", "value": "1234", "secret": true }
+///         ],
+///         "history": [
+///             { "password": "yyyyyyyy", "replaced_at": 23145546 }
+///         ],
+///         "otp": { "secret": "JBSWY3DPEHPK3PXP", "algorithm": "SHA1", "digits": 6, "period": 30 },
+///         "policy": {
+///             "length": 16, "lower": 1, "upper": 1, "digit": 1, "symbol": 2,
+///             "symbols": "@#$", "avoid_ambiguous": true
+///         }
+///     ]
+/// }
+/// ```
+///
+/// `url`, `notes`, `tags`, `fields`, `history`, `otp` and `policy` are optional, files written
+/// before they existed load just fine.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Schema {
+    passwords: Vec<Password>,
+}
+
+impl Schema {
+    fn new() -> Schema {
+        Schema {
+            passwords: Vec::new(),
+        }
+    }
+}
+
+/// A custom key/value field, ie: a PIN or a security question
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub value: SafeString,
+    /// Secret fields are never displayed unless asked for explicitly
+    #[serde(default)]
+    pub secret: bool,
+}
+
+/// A password that was replaced, kept around in case the new one doesn't work out
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PreviousPassword {
+    pub password: SafeString,
+    pub replaced_at: ffi::time_t,
+}
+
+/// Number of previous passwords kept for each app
+pub const HISTORY_LEN: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Password {
+    pub name: String,
+    pub username: String,
+    pub password: SafeString,
+    pub created_at: ffi::time_t,
+    pub updated_at: ffi::time_t,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+    /// Previous passwords, most recent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PreviousPassword>,
+    /// Secret for two factor authentication codes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otp: Option<Otp>,
+    /// Rules for the characters of the password, used again when regenerating it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<Policy>,
+}
+
+impl Password {
+    pub fn new<IS1: Into<String>, IS2: Into<String>, ISS: Into<SafeString>>(
+        name: IS1,
+        username: IS2,
+        password: ISS,
+    ) -> Password {
+        let timestamp = ffi::time();
+        Password {
+            name: name.into(),
+            username: username.into(),
+            password: password.into(),
+            created_at: timestamp,
+            updated_at: timestamp,
+            url: None,
+            notes: None,
+            tags: Vec::new(),
+            fields: Vec::new(),
+            history: Vec::new(),
+            otp: None,
+            policy: None,
+        }
+    }
+
+    /// Finds a custom field by name, without regard to casing
+    pub fn get_field(&self, name: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Adds a custom field, or replaces the one with the same name
+    pub fn set_field(&mut self, field: Field) {
+        match self
+            .fields
+            .iter_mut()
+            .find(|f| f.name.to_lowercase() == field.name.to_lowercase())
+        {
+            Some(existing) => *existing = field,
+            None => self.fields.push(field),
+        }
+    }
+
+    /// Removes a custom field, returns whether it existed
+    pub fn remove_field(&mut self, name: &str) -> bool {
+        let len = self.fields.len();
+        self.fields
+            .retain(|field| field.name.to_lowercase() != name.to_lowercase());
+        self.fields.len() != len
+    }
+}
+
+/// Reads the passwords from their JSON encoding
+fn parse_schema(json_schema: &SafeVec) -> Result<Schema, PasswordError> {
+    let encoded =
+        SafeString::from_string(String::from_utf8_lossy(json_schema.deref()).into_owned());
+    let s: Result<Schema, Error> = serde_json::from_str(encoded.deref());
+    s.map_err(|_| PasswordError::InvalidJsonError)
+}
+
+/// The parts of a Rooster file, as read from disk
+struct EncryptedFile {
+    version: u32,
+    kdf: Kdf,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    key_check: [u8; KEY_CHECK_LEN],
+    blob: Vec<u8>,
+}
+
+impl EncryptedFile {
+    fn parse(input: &[u8]) -> Result<EncryptedFile, PasswordError> {
+        let mut reader = Cursor::new(input);
+
+        // Version taken from network byte order (big endian).
+        let version = reader.read_u32::<BigEndian>()?;
+        if version > VERSION {
+            return Err(PasswordError::OutdatedRoosterBinaryError);
+        } else if version == 2 {
+            return Err(PasswordError::NeedUpgradeErrorFromV2);
+        } else if version < VERSION {
+            return Err(PasswordError::NeedUpgradeErrorFromV1);
+        }
+
+        // Read the key derivation params.
+        let kdf = match reader.read_u8()? {
+            KDF_SCRYPT => Kdf::Scrypt {
+                log2_n: reader.read_u8()?,
+                r: reader.read_u32::<BigEndian>()?,
+                p: reader.read_u32::<BigEndian>()?,
+            },
+            KDF_ARGON2ID => Kdf::Argon2id {
+                memory: reader.read_u32::<BigEndian>()?,
+                iterations: reader.read_u32::<BigEndian>()?,
+                parallelism: reader.read_u32::<BigEndian>()?,
+            },
+            // A key derivation function we don't know about was probably added in a newer
+            // version of Rooster.
+            _ => return Err(PasswordError::OutdatedRoosterBinaryError),
+        };
+        kdf.check().map_err(|_| PasswordError::CorruptionError)?;
+
+        // Read the old salt.
+        let mut salt: [u8; SALT_LEN] = [0u8; SALT_LEN];
+        reader.read_exact(&mut salt)?;
+
+        // Read the old nonce.
+        let mut nonce: [u8; NONCE_LEN] = [0u8; NONCE_LEN];
+        reader.read_exact(&mut nonce)?;
+
+        // Read the key check.
+        let mut key_check: [u8; KEY_CHECK_LEN] = [0u8; KEY_CHECK_LEN];
+        reader.read_exact(&mut key_check)?;
+
+        // The encrypted password data.
+        let mut blob: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut blob)?;
+
+        Ok(EncryptedFile {
+            version,
+            kdf,
+            salt,
+            nonce,
+            key_check,
+            blob,
+        })
+    }
+
+    /// Checks the key against the key check, and returns the header on success
+    fn check_key(&self, key: &[u8]) -> Result<Vec<u8>, PasswordError> {
+        let header = header(self.version, self.kdf, &self.salt, &self.nonce)?;
+        let authentication_subkey = generate_subkey(key, AUTHENTICATION_SUBKEY_ID);
+        let verification = unsafe {
+            crypto_auth_hmacsha512256_verify(
+                self.key_check.as_ptr() as *const c_uchar,
+                header.as_ptr() as *const c_uchar,
+                header.len() as c_ulonglong,
+                authentication_subkey.as_ptr() as *const c_uchar,
+            )
+        };
+        if verification != 0 {
+            return Err(PasswordError::DecryptionError);
+        }
+        Ok(header)
+    }
+
+    /// Decrypts the passwords, as JSON
+    fn decrypt(&self, key: &[u8]) -> Result<SafeVec, PasswordError> {
+        // Check the master password before trying to decrypt anything, so that we can tell
+        // a wrong password apart from a corrupted file.
+        let header = self.check_key(key)?;
+
+        // Decrypt the data, which also checks that nothing has been tampered with.
+        let encryption_subkey = generate_subkey(key, ENCRYPTION_SUBKEY_ID);
+        decrypt(
+            encryption_subkey.deref(),
+            &header,
+            &self.nonce,
+            self.blob.deref(),
+        )
+    }
+}
+
+/// Encrypts the passwords as JSON into a Rooster file, with a new nonce
+fn encrypt_file(
+    key: &[u8],
+    kdf: Kdf,
+    salt: &[u8],
+    json_schema: &[u8],
+) -> Result<Vec<u8>, PasswordError> {
+    let nonce = generate_random_nonce()?;
+    let header = header(VERSION, kdf, salt, &nonce)?;
+    let encryption_subkey = generate_subkey(key, ENCRYPTION_SUBKEY_ID);
+    let encrypted = encrypt(encryption_subkey.deref(), &header, &nonce, json_schema)?;
+    let authentication_subkey = generate_subkey(key, AUTHENTICATION_SUBKEY_ID);
+    let key_check = key_check(authentication_subkey.deref(), &header);
+
+    let mut contents: Vec<u8> = Vec::new();
+
+    // Write the version, key derivation params, salt and nonce.
+    contents.write_all(&header)?;
+
+    // Write the key check.
+    contents.write_all(&key_check)?;
+
+    // Write the encrypted password data.
+    contents.write_all(&encrypted)?;
+
+    Ok(contents)
+}
+
+/// Whether the key opens this password file, without decrypting it
+pub fn key_matches(key: &[u8], input: &[u8]) -> bool {
+    match EncryptedFile::parse(input) {
+        Ok(encrypted) => encrypted.check_key(key).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Decrypts the passwords of a Rooster file as JSON, with a key that was derived before
+///
+/// This is what the agent does for other Rooster commands, so that the key never leaves it.
+pub fn decrypt_with_key(key: &[u8], input: &[u8]) -> Result<SafeVec, PasswordError> {
+    EncryptedFile::parse(input)?.decrypt(key)
+}
+
+/// Encrypts passwords as JSON into a new version of a Rooster file, with the key that opens it
+///
+/// The key derivation parameters and the salt stay those of the file.
+pub fn encrypt_with_key(
+    key: &[u8],
+    input: &[u8],
+    json_schema: &[u8],
+) -> Result<Vec<u8>, PasswordError> {
+    let encrypted = EncryptedFile::parse(input)?;
+    encrypted.check_key(key)?;
+    encrypt_file(key, encrypted.kdf, &encrypted.salt, json_schema)
+}
+
+/// Where the key that encrypts a password store is
+#[derive(Clone)]
+enum Key {
+    /// Derived from the master password by this process
+    Derived(SafeVec),
+    /// Only known to the agent, which encrypts the password file it opened for us
+    #[cfg(unix)]
+    Agent(SafeVec),
+}
+
+#[derive(Clone)]
+pub struct PasswordStore {
+    key: Key,
+    kdf: Kdf,
+    salt: [u8; SALT_LEN],
+    schema: Schema,
+    // Unknown when the store was opened with a key from the agent.
+    master_password: Option<String>,
+    // Whether something changed since the password file was read, and needs to be saved.
+    dirty: bool,
+}
+
+/// Read and writes to a Rooster file
+///
+/// The Rooster file has the following format:
+/// - rooster version: u32, big endian
+/// - kdf:             u8, 1 for scrypt, 2 for argon2id
+/// - kdf params:      for scrypt
+///   - log2n:         u8
+///   - r:             u32, big endian
+///   - p:             u32, big endian
+/// - kdf params:      for argon2id
+///   - memory in KiB: u32, big endian
+///   - iterations:    u32, big endian
+///   - parallelism:   u32, big endian
+/// - salt:            256 bits
+/// - nonce:           192 bits
+/// - key check:       256 bits HMAC-SHA512256 of the above, with the authentication subkey
+/// - encrypted blob:  variable length XChaCha20-Poly1305, with the encryption subkey
+///
+/// Everything from the version to the nonce is authenticated as associated data of the
+/// encrypted blob.
+impl PasswordStore {
+    pub fn new(master_password: SafeString) -> Result<PasswordStore, PasswordError> {
+        PasswordStore::with_kdf(
+            master_password,
+            Kdf::Scrypt {
+                log2_n: SCRYPT_PARAM_LOG2_N,
+                r: SCRYPT_PARAM_R,
+                p: SCRYPT_PARAM_P,
+            },
+        )
+    }
+
+    pub fn with_kdf(master_password: SafeString, kdf: Kdf) -> Result<PasswordStore, PasswordError> {
+        let salt = generate_random_salt()?;
+        let key = generate_encryption_key(master_password.deref(), salt, kdf)?;
+
+        Ok(PasswordStore {
+            key: Key::Derived(key),
+            kdf,
+            salt,
+            schema: Schema::new(),
+            master_password: Some(master_password.into_inner()),
+            // A new store has never been saved.
+            dirty: true,
+        })
+    }
+
+    pub fn from_input(
+        master_password: SafeString,
+        input: SafeVec,
+    ) -> Result<PasswordStore, PasswordError> {
+        let encrypted = EncryptedFile::parse(input.deref())?;
+
+        // Derive a 256 bits encryption key from the password.
+        let key = generate_encryption_key(master_password.deref(), encrypted.salt, encrypted.kdf)?;
+
+        PasswordStore::decrypt(encrypted, key, Some(master_password.deref().into()))
+    }
+
+    /// Opens a password file that the agent decrypted
+    ///
+    /// The agent keeps the key, so it also encrypts the passwords when they are saved. Without
+    /// the master password, the key derivation parameters can't be changed.
+    #[cfg(unix)]
+    pub fn from_agent(
+        input: SafeVec,
+        json_schema: SafeVec,
+    ) -> Result<PasswordStore, PasswordError> {
+        let encrypted = EncryptedFile::parse(input.deref())?;
+        Ok(PasswordStore {
+            kdf: encrypted.kdf,
+            salt: encrypted.salt,
+            schema: parse_schema(&json_schema)?,
+            key: Key::Agent(input),
+            master_password: None,
+            dirty: false,
+        })
+    }
+
+    fn decrypt(
+        encrypted: EncryptedFile,
+        key: SafeVec,
+        master_password: Option<String>,
+    ) -> Result<PasswordStore, PasswordError> {
+        let json_schema = encrypted.decrypt(key.deref())?;
+
+        Ok(PasswordStore {
+            key: Key::Derived(key),
+            kdf: encrypted.kdf,
+            salt: encrypted.salt,
+            schema: parse_schema(&json_schema)?,
+            master_password,
+            dirty: false,
+        })
+    }
+
+    /// The key derived from the master password, to hand over to the agent
+    ///
+    /// Unknown when the agent opened the password file.
+    pub fn key(&self) -> Option<&[u8]> {
+        match &self.key {
+            Key::Derived(key) => Some(key.deref()),
+            #[cfg(unix)]
+            Key::Agent(_) => None,
+        }
+    }
+
+    /// Saves the password file, replacing the previous one atomically
+    pub fn sync(&self, path: &Path) -> Result<(), PasswordError> {
+        let contents = self.to_bytes()?;
+        safe_file::write_atomically(path, &contents)?;
+        Ok(())
+    }
+
+    /// Encrypts the passwords, as they are saved in the password file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, PasswordError> {
+        // This should never fail. The structs are all encodable.
+        let json_schema = match serde_json::to_string(&self.schema) {
+            Ok(json_schema) => json_schema,
+            Err(_) => {
+                return Err(PasswordError::InvalidJsonError);
+            }
+        };
+        let json_schema = SafeString::from_string(json_schema);
+
+        match &self.key {
+            Key::Derived(key) => encrypt_file(
+                key.deref(),
+                self.kdf,
+                &self.salt,
+                json_schema.deref().as_bytes(),
+            ),
+            #[cfg(unix)]
+            Key::Agent(input) => {
+                crate::agent::encrypt(input.deref(), json_schema.deref().as_bytes())
+                    .map_err(PasswordError::Io)
+            }
+        }
+    }
+
+    /// Whether the store changed since it was read, and needs to be synced
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn get_all_passwords(&self) -> Vec<&Password> {
+        let mut passwords: Vec<&Password> = self.schema.passwords.iter().collect();
+
+        passwords.sort_by_key(|p| p.name.to_lowercase());
+
+        passwords
+    }
+
+    /// Adds a password to the file.
+    pub fn add_password(&mut self, password: Password) -> Result<(), PasswordError> {
+        if password.password.is_empty() {
+            return Err(PasswordError::EmptyPasswordError);
+        }
+        if self.has_password(password.name.deref()) {
+            return Err(PasswordError::AppExistsError);
+        }
+        self.schema.passwords.push(password);
+        self.dirty = true;
+        Ok(())
+    }
+
+    pub fn delete_password(&mut self, name: &str) -> Result<Password, PasswordError> {
+        let p = self
+            .get_password(name)
+            .ok_or(PasswordError::NoSuchAppError)?;
+
+        let mut i = 0;
+        while i < self.schema.passwords.len() {
+            if self.schema.passwords[i].name == p.name {
+                self.dirty = true;
+                return Ok(self.schema.passwords.remove(i));
+            }
+            i += 1;
+        }
+        unreachable!();
+    }
+
+    /// Passwords whose name matches the query, the best matches first
+    pub fn search_passwords(&self, query: &str) -> Vec<&Password> {
+        self.rank_passwords(query)
+            .into_iter()
+            .map(|(_, password)| password)
+            .collect()
+    }
+
+    /// Like `search_passwords`, with the score of each match
+    ///
+    /// Matches with the same score are sorted by name.
+    pub fn rank_passwords(&self, query: &str) -> Vec<(i32, &Password)> {
+        let mut passwords: Vec<(i32, &Password)> = self
+            .schema
+            .passwords
+            .iter()
+            .filter_map(|p| Some((fuzzy::score(query, &p.name)?, p)))
+            .collect();
+
+        passwords.sort_by(|(score_a, a), (score_b, b)| {
+            score_b
+                .cmp(score_a)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+
+        passwords
+    }
+
+    pub fn get_password(&self, name: &str) -> Option<Password> {
+        'passwords_loop: for p in &self.schema.passwords {
+            // Since the app name must be the same, we need the same length.
+            if p.name.len() != name.len() {
+                continue 'passwords_loop;
+            }
+
+            // We're looking for the exact same app name, without regard to casing.
+            let mut i: usize = 0;
+            while i < p.name.len() {
+                let c1 = p.name.chars().nth(i).map(|c| c.to_lowercase().next());
+                let c2 = name.chars().nth(i).map(|c| c.to_lowercase().next());
+                if c1 != c2 {
+                    continue 'passwords_loop;
+                }
+                i += 1;
+            }
+            return Some(p.clone());
+        }
+        None
+    }
+
+    pub fn has_password(&self, name: &str) -> bool {
+        self.get_password(name).is_some()
+    }
+
+    pub fn change_password(
+        &mut self,
+        app_name: &str,
+        closure: &dyn Fn(Password) -> Password,
+    ) -> Result<Password, PasswordError> {
+        let old_password = self.delete_password(app_name)?;
+        let mut new_password = closure(old_password.clone());
+
+        // Keep the previous password around, in case the new one doesn't work out.
+        if new_password.password != old_password.password {
+            new_password.history.insert(
+                0,
+                PreviousPassword {
+                    password: old_password.password.clone(),
+                    replaced_at: new_password.updated_at,
+                },
+            );
+            new_password.history.truncate(HISTORY_LEN);
+        }
+        match self.add_password(new_password.clone()) {
+            Ok(_) => Ok(new_password),
+            Err(err) => {
+                // Password was not added, we'll add the old one back
+                self.add_password(old_password)?;
+                Err(err)
+            }
+        }
+    }
+
+    pub fn change_master_password(&mut self, master_password: &str) -> Result<(), PasswordError> {
+        self.key = Key::Derived(generate_encryption_key(
+            master_password,
+            self.salt,
+            self.kdf,
+        )?);
+        self.master_password = Some(master_password.into());
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn master_password(&self) -> &str {
+        self.master_password
+            .as_deref()
+            .expect("the master password is needed to derive a new key")
+    }
+
+    pub fn change_scrypt_params(
+        &mut self,
+        scrypt_log2_n: u8,
+        scrypt_r: u32,
+        scrypt_p: u32,
+    ) -> Result<(), PasswordError> {
+        self.change_kdf(Kdf::Scrypt {
+            log2_n: scrypt_log2_n,
+            r: scrypt_r,
+            p: scrypt_p,
+        })
+    }
+
+    pub fn change_argon2id_params(
+        &mut self,
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> Result<(), PasswordError> {
+        self.change_kdf(Kdf::Argon2id {
+            memory,
+            iterations,
+            parallelism,
+        })
+    }
+
+    fn change_kdf(&mut self, kdf: Kdf) -> Result<(), PasswordError> {
+        self.key = Key::Derived(generate_encryption_key(
+            self.master_password(),
+            self.salt,
+            kdf,
+        )?);
+        self.kdf = kdf;
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::password::v3::{
+        decrypt_with_key, encrypt_with_key, generate_encryption_key, generate_random_nonce,
+        generate_random_salt, generate_subkey, key_matches, Field, Kdf, Password, PasswordStore,
+        PreviousPassword, AUTHENTICATION_SUBKEY_ID, ENCRYPTION_SUBKEY_ID, HISTORY_LEN,
+        SCRYPT_PARAM_LOG2_N, SCRYPT_PARAM_P, SCRYPT_PARAM_R,
+    };
+    use crate::password::PasswordError;
+    use crate::rutil::safe_string::SafeString;
+    use crate::rutil::safe_vec::SafeVec;
+
+    fn sync_and_read(store: &PasswordStore) -> SafeVec {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.rooster");
+        store.sync(&path).unwrap();
+        SafeVec::new(std::fs::read(&path).unwrap())
+    }
+
+    #[test]
+    fn test_generate_random_nonce_has_right_length() {
+        assert_eq!(generate_random_nonce().unwrap().len(), 24);
+    }
+
+    #[test]
+    fn test_generate_random_salt_has_right_length() {
+        assert_eq!(generate_random_salt().unwrap().len(), 32);
+    }
+
+    const SCRYPT: Kdf = Kdf::Scrypt {
+        log2_n: SCRYPT_PARAM_LOG2_N,
+        r: SCRYPT_PARAM_R,
+        p: SCRYPT_PARAM_P,
+    };
+
+    const ARGON2ID: Kdf = Kdf::Argon2id {
+        memory: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_generate_encryption_key_returns_256_bits_key() {
+        assert_eq!(
+            generate_encryption_key("hello world", generate_random_salt().unwrap(), SCRYPT)
+                .unwrap()
+                .len(),
+            32
+        );
+        assert_eq!(
+            generate_encryption_key("hello world", generate_random_salt().unwrap(), ARGON2ID)
+                .unwrap()
+                .len(),
+            32
+        );
+    }
+
+    #[test]
+    fn test_generate_encryption_key_depends_on_kdf() {
+        let salt = generate_random_salt().unwrap();
+        assert_ne!(
+            generate_encryption_key("hello world", salt, SCRYPT).unwrap(),
+            generate_encryption_key("hello world", salt, ARGON2ID).unwrap()
+        );
+        assert_eq!(
+            generate_encryption_key("hello world", salt, ARGON2ID).unwrap(),
+            generate_encryption_key("hello world", salt, ARGON2ID).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_subkey_returns_different_256_bits_keys() {
+        let key = generate_encryption_key("hello world", generate_random_salt().unwrap(), SCRYPT)
+            .unwrap();
+        let encryption_subkey = generate_subkey(&key, ENCRYPTION_SUBKEY_ID);
+        let authentication_subkey = generate_subkey(&key, AUTHENTICATION_SUBKEY_ID);
+        assert_eq!(encryption_subkey.len(), 32);
+        assert_eq!(authentication_subkey.len(), 32);
+        assert_ne!(encryption_subkey, authentication_subkey);
+        assert_ne!(encryption_subkey, key);
+    }
+
+    #[test]
+    fn test_sync_and_read_back() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        let input = sync_and_read(&store);
+        assert_eq!(&input[0..4], &[0, 0, 0, 3]);
+
+        let store =
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), input).unwrap();
+        assert_eq!(store.get_all_passwords().len(), 1);
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+    }
+
+    #[test]
+    fn test_dirty_flag() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store.is_dirty());
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        let input = sync_and_read(&store);
+        let mut store =
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), input).unwrap();
+        assert!(!store.is_dirty());
+
+        // Reading doesn't change anything
+        store.get_all_passwords();
+        store.search_passwords("nm");
+        store.get_password("name");
+        assert!(!store.is_dirty());
+
+        assert!(store.delete_password("name").is_ok());
+        assert!(store.is_dirty());
+    }
+
+    #[test]
+    fn test_sync_and_read_back_argon2id() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        store.change_argon2id_params(1024, 1, 1).unwrap();
+
+        let input = sync_and_read(&store);
+        assert_eq!(&input[0..5], &[0, 0, 0, 3, 2]);
+        assert_eq!(&input[5..17], &[0, 0, 4, 0, 0, 0, 0, 1, 0, 0, 0, 1]);
+
+        let store =
+            PasswordStore::from_input(SafeString::from_string("****".to_owned()), input).unwrap();
+        assert_eq!(store.kdf, ARGON2ID);
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+    }
+
+    #[test]
+    fn test_unknown_kdf() {
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let mut input = sync_and_read(&store);
+        input[4] = 42;
+
+        match PasswordStore::from_input(SafeString::from_string("****".to_owned()), input) {
+            Err(PasswordError::OutdatedRoosterBinaryError) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_invalid_kdf_params() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store.change_argon2id_params(1024, 1, 1).unwrap();
+        let input = sync_and_read(&store);
+
+        // Huge memory, no iterations and no parallelism
+        for (offset, value) in [(5, u32::MAX), (9, 0), (13, 0)] {
+            let mut input = SafeVec::new(input.to_vec());
+            input[offset..offset + 4].copy_from_slice(&value.to_be_bytes());
+            match PasswordStore::from_input(SafeString::from_string("****".to_owned()), input) {
+                Err(PasswordError::CorruptionError) => {}
+                _ => panic!(),
+            }
+        }
+
+        // Scrypt with 2^63 rounds
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let mut input = sync_and_read(&store);
+        input[5] = 63;
+        match PasswordStore::from_input(SafeString::from_string("****".to_owned()), input) {
+            Err(PasswordError::CorruptionError) => {}
+            _ => panic!(),
+        }
+
+        assert!(store
+            .clone()
+            .change_argon2id_params(u32::MAX, 1, 1)
+            .is_err());
+    }
+
+    #[test]
+    fn test_wrong_master_password() {
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let input = sync_and_read(&store);
+
+        match PasswordStore::from_input(SafeString::from_string("nope".to_owned()), input) {
+            Err(PasswordError::DecryptionError) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt_with_key() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        store
+            .add_password(Password::new("Youtube", "yt@example.com", "abcd"))
+            .unwrap();
+        let key = store.key().unwrap().to_vec();
+        let input = sync_and_read(&store);
+
+        let json_schema = decrypt_with_key(&key, &input).unwrap();
+        assert!(key_matches(&key, &input));
+        assert!(!key_matches(&[0u8; 32], &input));
+        assert!(matches!(
+            decrypt_with_key(&[0u8; 32], &input),
+            Err(PasswordError::DecryptionError)
+        ));
+
+        // What the agent encrypts opens with the master password
+        let contents = encrypt_with_key(&key, &input, &json_schema).unwrap();
+        let reopened = PasswordStore::from_input(
+            SafeString::from_string("****".to_owned()),
+            SafeVec::new(contents),
+        )
+        .unwrap();
+        assert!(reopened.has_password("Youtube"));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        let input = sync_and_read(&store);
+
+        // Tampering with the encrypted blob
+        let mut corrupted = input.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        match PasswordStore::from_input(SafeString::from_string("****".to_owned()), corrupted) {
+            Err(PasswordError::CorruptionError) => {}
+            _ => panic!(),
+        }
+
+        // Tampering with the nonce, which is part of the associated data
+        let mut corrupted = input;
+        corrupted[50] ^= 1;
+        match PasswordStore::from_input(SafeString::from_string("****".to_owned()), corrupted) {
+            Err(PasswordError::DecryptionError) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_create_password_store() {
+        let store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert_eq!(store.get_all_passwords().len(), 0);
+    }
+
+    #[test]
+    fn test_add_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        // need a wrap around the immutable borrow so the borrow checker is happy
+        {
+            // only the 1 password is here
+            let passwords = store.get_all_passwords();
+            assert_eq!(passwords.len(), 1);
+
+            // is had the right information
+            let p = passwords[0];
+            assert_eq!(p.name, "name");
+            assert_eq!(p.username, "username");
+            assert_eq!(p.password, "password".into());
+            assert_eq!(p.updated_at, p.created_at);
+        }
+
+        // cant add two passwords with same app name
+        match store.add_password(Password::new("name", "username", "password")) {
+            Err(PasswordError::AppExistsError) => {}
+            _ => panic!(),
+        }
+
+        // empty password => not allowed
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", ""))
+            .is_err());
+    }
+
+    #[test]
+    fn test_password_details_are_optional() {
+        let password: Password = serde_json::from_str(
+            r#"{"name":"name","username":"username","password":"password","created_at":1,"updated_at":2}"#,
+        )
+        .unwrap();
+        assert_eq!(password.url, None);
+        assert_eq!(password.notes, None);
+        assert!(password.tags.is_empty());
+        assert!(password.fields.is_empty());
+
+        // Empty details aren't written either
+        assert!(!serde_json::to_string(&password).unwrap().contains("url"));
+    }
+
+    #[test]
+    fn test_password_fields() {
+        let mut password = Password::new("name", "username", "password");
+        password.set_field(Field {
+            name: "PIN".to_owned(),
+            value: "1234".into(),
+            secret: true,
+        });
+        password.set_field(Field {
+            name: "pin".to_owned(),
+            value: "5678".into(),
+            secret: true,
+        });
+        assert_eq!(password.fields.len(), 1);
+        assert_eq!(password.get_field("Pin").unwrap().value, "5678".into());
+
+        assert!(password.remove_field("PIN"));
+        assert!(!password.remove_field("PIN"));
+        assert!(password.get_field("pin").is_none());
+    }
+
+    #[test]
+    fn test_change_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        // the previous password is kept in the history
+        let mut expected = Password::new("newname", "username", "newpassword");
+        expected.history = vec![PreviousPassword {
+            password: "password".into(),
+            replaced_at: expected.updated_at,
+        }];
+        assert_eq!(
+            store
+                .change_password("name", &|p| {
+                    // change app name and password, keep username
+                    Password::new("newname", p.username, "newpassword")
+                })
+                .unwrap(),
+            expected
+        );
+        assert_eq!(store.get_all_passwords().len(), 1);
+        assert_eq!(store.get_all_passwords()[0].name, "newname");
+        assert_eq!(store.get_all_passwords()[0].username, "username");
+        assert_eq!(store.get_all_passwords()[0].password, "newpassword".into());
+
+        // case insensitive works too
+        assert_eq!(store.change_password("newname", &|p| p).unwrap(), expected);
+        assert_eq!(store.get_all_passwords().len(), 1);
+        assert_eq!(store.get_all_passwords()[0].name, "newname");
+        assert_eq!(store.get_all_passwords()[0].username, "username");
+        assert_eq!(store.get_all_passwords()[0].password, "newpassword".into());
+
+        // empty password => do not change anything
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        assert!(store
+            .change_password("name", &|p| {
+                // change app name and password, keep username
+                Password::new(p.username.clone(), p.username.clone(), "")
+            })
+            .is_err());
+        assert_eq!(store.get_all_passwords()[0].name, "name");
+        assert_eq!(store.get_all_passwords()[0].username, "username");
+        assert_eq!(store.get_all_passwords()[0].password, "password".into());
+    }
+
+    #[test]
+    fn test_change_password_keeps_history() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password0"))
+            .is_ok());
+
+        for i in 1..=HISTORY_LEN + 1 {
+            let new_password = format!("password{}", i);
+            let p = store
+                .change_password("name", &|p| Password {
+                    password: new_password.clone().into(),
+                    ..p
+                })
+                .unwrap();
+            assert_eq!(p.history[0].password, format!("password{}", i - 1).into());
+        }
+
+        let p = store.get_password("name").unwrap();
+        assert_eq!(p.history.len(), HISTORY_LEN);
+        assert_eq!(
+            p.history[HISTORY_LEN - 1].password,
+            "password1".into(),
+            "the oldest passwords are forgotten"
+        );
+
+        // Changing something else than the password doesn't add to the history
+        let p = store
+            .change_password("name", &|p| Password {
+                username: "other".to_owned(),
+                ..p
+            })
+            .unwrap();
+        assert_eq!(p.history.len(), HISTORY_LEN);
+        assert_eq!(p.history[0].password, "password10".into());
+    }
+
+    #[test]
+    fn test_delete_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+
+        assert!(store
+            .add_password(Password::new("name1", "username", "password"))
+            .is_ok());
+        assert!(store
+            .add_password(Password::new("name2", "username", "password"))
+            .is_ok());
+        assert_eq!(store.get_all_passwords().len(), 2);
+
+        assert_eq!(
+            store.delete_password("name1").unwrap(),
+            Password::new("name1", "username", "password")
+        );
+        assert!(store.get_password("name1").is_none());
+        assert_eq!(store.get_all_passwords().len(), 1);
+        // case insensitive works too
+        assert_eq!(
+            store.delete_password("NAME2").unwrap(),
+            Password::new("name2", "username", "password")
+        );
+        assert!(store.get_password("name2").is_none());
+        assert_eq!(store.get_all_passwords().len(), 0);
+    }
+
+    #[test]
+    fn test_get_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+
+        assert_eq!(store.get_password("name"), None);
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        assert_eq!(
+            store.get_password("name").unwrap(),
+            Password::new("name", "username", "password")
+        );
+        assert_eq!(
+            store.get_password("NaMe").unwrap(),
+            Password::new("name", "username", "password")
+        );
+    }
+
+    #[test]
+    fn test_has_password() {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+
+        assert!(!store.has_password("name"));
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+        assert!(store.has_password("name"));
+    }
+}
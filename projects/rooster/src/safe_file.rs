@@ -0,0 +1,210 @@
+use std::fs::File;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Number of backups kept when no other number is set with `ROOSTER_BACKUPS`
+pub const DEFAULT_BACKUP_COUNT: usize = 3;
+
+/// Replaces the contents of a file without ever leaving it half written
+///
+/// The contents are written to a temporary file in the same directory, which is then renamed
+/// over the original file. If anything goes wrong, the original file is left untouched.
+///
+/// If the path is a symlink, the file it points to is replaced, and the link is kept. The
+/// permissions of the original file are kept too.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> IoResult<()> {
+    let target = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = parent_dir(&target);
+
+    let mut temp_file = tempfile::NamedTempFile::new_in(dir)?;
+    if let Ok(metadata) = std::fs::metadata(&target) {
+        temp_file
+            .as_file()
+            .set_permissions(metadata.permissions())?;
+    }
+    temp_file.write_all(contents)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(&target).map_err(|err| err.error)?;
+
+    // Make sure the rename itself has hit the disk.
+    sync_dir(dir)
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> IoResult<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> IoResult<()> {
+    Ok(())
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Path of the n-th backup of a file, ie: `~/.passwords.rooster.bak.1`
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut backup = path.as_os_str().to_os_string();
+    backup.push(format!(".bak.{}", n));
+    PathBuf::from(backup)
+}
+
+/// Saves a copy of the file as backup number 1, shifting the older backups by one
+///
+/// At most `count` backups are kept, the oldest ones are deleted, including those left over
+/// from a higher count. A count of 0 disables backups, and leaves existing ones alone.
+pub fn rotate_backups(path: &Path, count: usize) -> IoResult<()> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let mut oldest = count;
+    while backup_path(path, oldest).exists() {
+        std::fs::remove_file(backup_path(path, oldest))?;
+        oldest += 1;
+    }
+
+    for n in (1..count).rev() {
+        let backup = backup_path(path, n);
+        if backup.exists() {
+            std::fs::rename(&backup, backup_path(path, n + 1))?;
+        }
+    }
+
+    // The backup is written atomically too, so that a crash never leaves a truncated backup.
+    write_atomically(&backup_path(path, 1), std::fs::read(path)?.as_slice())
+}
+
+/// A backup of the password file
+pub struct Backup {
+    pub number: usize,
+    pub path: PathBuf,
+    pub modified: SystemTime,
+}
+
+/// Lists existing backups, most recent first
+pub fn list_backups(path: &Path) -> IoResult<Vec<Backup>> {
+    let mut backups = vec![];
+    let mut number = 1;
+    loop {
+        let backup = backup_path(path, number);
+        if !backup.exists() {
+            return Ok(backups);
+        }
+        let modified = backup.metadata()?.modified()?;
+        backups.push(Backup {
+            number,
+            path: backup,
+            modified,
+        });
+        number += 1;
+    }
+}
+
+/// Parses the number of backups to keep, as set in the `ROOSTER_BACKUPS` environment variable
+pub fn parse_backup_count(value: Option<&str>) -> IoResult<usize> {
+    match value {
+        None => Ok(DEFAULT_BACKUP_COUNT),
+        Some(value) => value.trim().parse::<usize>().map_err(|_| {
+            IoError::new(
+                IoErrorKind::InvalidInput,
+                format!("ROOSTER_BACKUPS must be a number, got \"{}\"", value),
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{backup_path, list_backups, parse_backup_count, rotate_backups, write_atomically};
+    use std::path::Path;
+
+    #[test]
+    fn test_backup_path() {
+        assert_eq!(
+            backup_path(Path::new("/home/me/.passwords.rooster"), 2),
+            Path::new("/home/me/.passwords.rooster.bak.2")
+        );
+    }
+
+    #[test]
+    fn test_write_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.rooster");
+
+        write_atomically(&path, b"first").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"first");
+        write_atomically(&path, b"second").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+
+        // No temporary file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_rotate_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("passwords.rooster");
+
+        for contents in &["1", "2", "3", "4"] {
+            rotate_backups(&path, 2).unwrap();
+            write_atomically(&path, contents.as_bytes()).unwrap();
+        }
+
+        let backups = list_backups(&path).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(std::fs::read(&backups[0].path).unwrap(), b"3");
+        assert_eq!(std::fs::read(&backups[1].path).unwrap(), b"2");
+        assert!(!backup_path(&path, 3).exists());
+
+        // No backups at all
+        rotate_backups(&path, 0).unwrap();
+        assert_eq!(std::fs::read(&backup_path(&path, 1)).unwrap(), b"3");
+
+        // Backups above a lowered count are deleted
+        for _ in 0..3 {
+            rotate_backups(&path, 4).unwrap();
+        }
+        assert_eq!(list_backups(&path).unwrap().len(), 4);
+        rotate_backups(&path, 2).unwrap();
+        assert_eq!(list_backups(&path).unwrap().len(), 2);
+        assert!(!backup_path(&path, 3).exists());
+        assert!(!backup_path(&path, 4).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_keeps_symlinks_and_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("vault.rooster");
+        let link = dir.path().join("passwords.rooster");
+        write_atomically(&target, b"first").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomically(&link, b"second").unwrap();
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read(&target).unwrap(), b"second");
+        let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn test_parse_backup_count() {
+        assert_eq!(parse_backup_count(None).unwrap(), 3);
+        assert_eq!(parse_backup_count(Some("0")).unwrap(), 0);
+        assert_eq!(parse_backup_count(Some("10")).unwrap(), 10);
+        assert!(parse_backup_count(Some("ten")).is_err());
+    }
+}
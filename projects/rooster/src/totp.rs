@@ -0,0 +1,356 @@
+use crate::rutil::safe_string::SafeString;
+use crate::rutil::safe_vec::SafeVec;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::ops::Deref;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// A TOTP secret (RFC 6238), as shown by websites when enabling two factor authentication
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "OtpFields")]
+pub struct Otp {
+    /// The shared secret, base32 encoded, uppercase and without padding
+    pub secret: SafeString,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+    pub period: u64,
+}
+
+/// An `Otp` as it is deserialized, ie: from a JSON import or a bundle, before it is checked
+#[derive(Deserialize)]
+struct OtpFields {
+    secret: SafeString,
+    #[serde(default = "default_algorithm")]
+    algorithm: Algorithm,
+    #[serde(default = "default_digits")]
+    digits: u32,
+    #[serde(default = "default_period")]
+    period: u64,
+}
+
+impl TryFrom<OtpFields> for Otp {
+    type Error = String;
+
+    fn try_from(fields: OtpFields) -> Result<Otp, String> {
+        Ok(Otp {
+            secret: fields.secret,
+            algorithm: fields.algorithm,
+            digits: check_digits(fields.digits)?,
+            period: check_period(fields.period)?,
+        })
+    }
+}
+
+fn default_algorithm() -> Algorithm {
+    Algorithm::Sha1
+}
+
+fn default_digits() -> u32 {
+    6
+}
+
+fn default_period() -> u64 {
+    30
+}
+
+fn check_digits(digits: u32) -> Result<u32, String> {
+    if (6..=10).contains(&digits) {
+        Ok(digits)
+    } else {
+        Err(String::from("The digits must be between 6 and 10"))
+    }
+}
+
+fn check_period(period: u64) -> Result<u64, String> {
+    if period > 0 {
+        Ok(period)
+    } else {
+        Err(String::from("The period must be > 0"))
+    }
+}
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Decodes base32 (RFC 4648), ignoring padding, spaces and casing
+fn base32_decode(input: &str) -> Option<SafeVec> {
+    let mut output = SafeVec::new(Vec::new());
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in input.bytes() {
+        if c == b'=' || c == b' ' {
+            continue;
+        }
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.inner_mut().push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    if output.is_empty() {
+        None
+    } else {
+        Some(output)
+    }
+}
+
+/// Decodes the %XX escapes in otpauth URIs
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 3 <= bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                output.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn normalize_secret(secret: &str) -> Result<SafeString, String> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| *c != ' ' && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let secret = SafeString::from_string(secret);
+    if base32_decode(secret.deref()).is_none() {
+        return Err(String::from("The secret must be valid base32"));
+    }
+    Ok(secret)
+}
+
+impl Otp {
+    /// Reads an `otpauth://totp/...` URI, or a raw base32 secret with the default parameters
+    pub fn parse(input: &str) -> Result<Otp, String> {
+        let input = input.trim();
+
+        let uri = match input.strip_prefix("otpauth://") {
+            Some(uri) => uri,
+            None => {
+                return Ok(Otp {
+                    secret: normalize_secret(input)?,
+                    algorithm: default_algorithm(),
+                    digits: default_digits(),
+                    period: default_period(),
+                })
+            }
+        };
+
+        if !uri.to_lowercase().starts_with("totp/") {
+            return Err(String::from(
+                "Only time based one-time passwords (otpauth://totp/...) are supported",
+            ));
+        }
+
+        let mut otp = Otp {
+            secret: SafeString::new(),
+            algorithm: default_algorithm(),
+            digits: default_digits(),
+            period: default_period(),
+        };
+        let query = uri.split_once('?').map(|(_, query)| query).unwrap_or("");
+        for parameter in query.split('&') {
+            let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            let value = percent_decode(value);
+            match key.to_lowercase().as_str() {
+                "secret" => otp.secret = normalize_secret(&value)?,
+                "algorithm" => {
+                    otp.algorithm = match value.to_uppercase().as_str() {
+                        "SHA1" => Algorithm::Sha1,
+                        "SHA256" => Algorithm::Sha256,
+                        "SHA512" => Algorithm::Sha512,
+                        _ => return Err(format!("Unknown algorithm \"{}\"", value)),
+                    }
+                }
+                "digits" => otp.digits = check_digits(value.parse::<u32>().unwrap_or(0))?,
+                "period" => otp.period = check_period(value.parse::<u64>().unwrap_or(0))?,
+                // The label, issuer, etc are not needed to generate codes.
+                _ => {}
+            }
+        }
+
+        if otp.secret.is_empty() {
+            return Err(String::from("The URI has no secret"));
+        }
+
+        Ok(otp)
+    }
+
+    /// Computes the code for a given time, in seconds since the UNIX epoch
+    pub fn code(&self, time: u64) -> Result<SafeString, String> {
+        let key = base32_decode(self.secret.deref())
+            .ok_or_else(|| String::from("The secret is not valid base32"))?;
+        let digest = match self.algorithm {
+            Algorithm::Sha1 => MessageDigest::sha1(),
+            Algorithm::Sha256 => MessageDigest::sha256(),
+            Algorithm::Sha512 => MessageDigest::sha512(),
+        };
+        let counter = time / self.period;
+
+        let hmac = (|| {
+            let key = PKey::hmac(key.deref())?;
+            let mut signer = Signer::new(digest, &key)?;
+            signer.update(&counter.to_be_bytes())?;
+            signer.sign_to_vec()
+        })()
+        .map_err(|err| err.to_string())?;
+        let hmac = SafeVec::new(hmac);
+
+        // Dynamic truncation, see RFC 4226, section 5.3
+        let offset = (hmac[hmac.len() - 1] & 0xf) as usize;
+        let binary = (u64::from(hmac[offset] & 0x7f) << 24)
+            | (u64::from(hmac[offset + 1]) << 16)
+            | (u64::from(hmac[offset + 2]) << 8)
+            | u64::from(hmac[offset + 3]);
+        let code = binary % 10u64.pow(self.digits);
+
+        Ok(SafeString::from_string(format!(
+            "{:0width$}",
+            code,
+            width = self.digits as usize
+        )))
+    }
+
+    /// Number of seconds the code for a given time stays valid
+    pub fn seconds_remaining(&self, time: u64) -> u64 {
+        self.period - time % self.period
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base32_decode, percent_decode, Algorithm, Otp};
+    use std::ops::Deref;
+
+    // Test vectors from RFC 6238, appendix B
+    const SECRET_SHA1: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    const SECRET_SHA256: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZA";
+    const SECRET_SHA512: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQGEZDGNA";
+
+    #[test]
+    fn test_base32_decode() {
+        assert_eq!(
+            base32_decode(SECRET_SHA1).unwrap().deref(),
+            b"12345678901234567890"
+        );
+        assert_eq!(
+            base32_decode("mzxw6ytb oi======").unwrap().deref(),
+            b"foobar"
+        );
+        assert!(base32_decode("not base32!").is_none());
+        assert!(base32_decode("").is_none());
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let vectors: &[(u64, &str, &str, &str)] = &[
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+
+        for (algorithm, secret, index) in &[
+            (Algorithm::Sha1, SECRET_SHA1, 1),
+            (Algorithm::Sha256, SECRET_SHA256, 2),
+            (Algorithm::Sha512, SECRET_SHA512, 3),
+        ] {
+            let otp = Otp {
+                secret: secret.to_string().into(),
+                algorithm: *algorithm,
+                digits: 8,
+                period: 30,
+            };
+            for vector in vectors {
+                let expected = match index {
+                    1 => vector.1,
+                    2 => vector.2,
+                    _ => vector.3,
+                };
+                assert_eq!(otp.code(vector.0).unwrap().deref(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_base32() {
+        let otp = Otp::parse("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap();
+        assert_eq!(otp.secret.deref(), SECRET_SHA1);
+        assert_eq!(otp.algorithm, Algorithm::Sha1);
+        assert_eq!(otp.digits, 6);
+        assert_eq!(otp.period, 30);
+        assert_eq!(otp.code(59).unwrap().deref(), "287082");
+        assert_eq!(otp.seconds_remaining(59), 1);
+
+        assert!(Otp::parse("not base32!").is_err());
+    }
+
+    #[test]
+    fn test_parse_uri() {
+        let otp = Otp::parse(&format!(
+            "otpauth://totp/Example:alice%40example.com?secret={}&issuer=Example&algorithm=SHA256&digits=8&period=60",
+            SECRET_SHA256
+        ))
+        .unwrap();
+        assert_eq!(otp.secret.deref(), SECRET_SHA256);
+        assert_eq!(otp.algorithm, Algorithm::Sha256);
+        assert_eq!(otp.digits, 8);
+        assert_eq!(otp.period, 60);
+        // Same counter as 59 seconds with a 30 seconds period
+        assert_eq!(otp.code(118).unwrap().deref(), "46119246");
+
+        assert!(Otp::parse("otpauth://hotp/Example?secret=GEZDGNBV&counter=1").is_err());
+        assert!(Otp::parse("otpauth://totp/Example?issuer=Example").is_err());
+        assert!(Otp::parse("otpauth://totp/Example?secret=GEZDGNBV&algorithm=MD5").is_err());
+        assert!(Otp::parse("otpauth://totp/Example?secret=GEZDGNBV&digits=4").is_err());
+        assert!(Otp::parse("otpauth://totp/Example?secret=GEZDGNBV&period=0").is_err());
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%40b.com"), "a@b.com");
+        assert_eq!(percent_decode("GEZDGNBV%3D"), "GEZDGNBV=");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_deserialize_checks_parameters() {
+        let otp: Otp =
+            serde_json::from_str(&format!("{{\"secret\":\"{}\"}}", SECRET_SHA1)).unwrap();
+        assert_eq!(otp.digits, 6);
+        assert_eq!(otp.period, 30);
+
+        for fields in ["\"period\":0", "\"digits\":4294967295", "\"digits\":0"] {
+            let json = format!("{{\"secret\":\"{}\",{}}}", SECRET_SHA1, fields);
+            assert!(serde_json::from_str::<Otp>(&json).is_err());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+fn list(input: &str, rooster_file: &std::path::PathBuf) -> i32 {
+    main_with_args(
+        &["rooster", "list"],
+        &mut CursorInputOutput::new("", input),
+        rooster_file,
+    )
+}
+
+// Everything happens in a single test, because the tests of a file share the environment
+#[test]
+fn test_agent() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let runtime_dir = tempfile::tempdir().unwrap();
+    let socket = runtime_dir.path().join("rooster-agent.sock");
+    std::env::set_var("XDG_RUNTIME_DIR", runtime_dir.path());
+
+    // Without an agent, locking does nothing
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "lock"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_ne!(0, list("", &rooster_file));
+
+    let agent_file = rooster_file.clone();
+    std::thread::spawn(move || {
+        main_with_args(
+            &["rooster", "agent", "--timeout", "2"],
+            &mut CursorInputOutput::new("", ""),
+            &agent_file,
+        )
+    });
+    let start = Instant::now();
+    while !socket.exists() && start.elapsed() < Duration::from_secs(10) {
+        sleep(Duration::from_millis(50));
+    }
+    assert_eq!(
+        0o600,
+        std::fs::metadata(&socket).unwrap().permissions().mode() & 0o777
+    );
+
+    // Only one agent at a time
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "agent"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    // The master password is asked once, then the agent knows it
+    assert_ne!(0, list("", &rooster_file));
+    assert_eq!(0, list("xxxx\n", &rooster_file));
+    assert_eq!(0, list("", &rooster_file));
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "abcd\n"),
+            &rooster_file
+        )
+    );
+
+    // The agent never hands out the key, only what it decrypted
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    let contents = std::fs::read(&rooster_file).unwrap();
+    stream.write_all(b"k").unwrap();
+    stream
+        .write_all(&(contents.len() as u32).to_be_bytes())
+        .unwrap();
+    stream.write_all(&contents).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    assert_eq!(response, &[2, 0, 0, 0, 0]);
+
+    // The agent follows changes of the master password
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\nyyyy\nyyyy\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(0, list("", &rooster_file));
+
+    // Locked right away
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "lock"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_ne!(0, list("", &rooster_file));
+
+    // Forgotten once not used for a while
+    assert_eq!(0, list("yyyy\n", &rooster_file));
+    assert_eq!(0, list("", &rooster_file));
+    sleep(Duration::from_secs(3));
+    assert_ne!(0, list("", &rooster_file));
+
+    std::env::remove_var("XDG_RUNTIME_DIR");
+}
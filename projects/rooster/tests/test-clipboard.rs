@@ -0,0 +1,281 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// Fake clipboard tools, which keep the clipboard in a file
+//
+// Rooster only gets the fake tools in its PATH, so that the real ones are never used.
+//
+// The primary selection is kept next to it, in a file ending with ".primary".
+const FAKE_WL_COPY: &str = "#!/bin/sh
+file=CLIPBOARD
+if [ \"$1\" = \"--primary\" ]; then file=CLIPBOARD.primary; shift; fi
+if [ \"$1\" = \"--clear\" ]; then : > $file; else cat > $file; fi
+";
+const FAKE_WL_PASTE: &str = "#!/bin/sh
+file=CLIPBOARD
+if [ \"$1\" = \"--primary\" ]; then file=CLIPBOARD.primary; fi
+cat $file
+";
+const FAKE_PBCOPY: &str = "#!/bin/sh\ncat > CLIPBOARD\n";
+const FAKE_PBPASTE: &str = "#!/bin/sh\ncat CLIPBOARD\n";
+const FAKE_CUSTOM: &str = "#!/bin/sh\nprintf '%s ' \"$1\" > CLIPBOARD\ncat >> CLIPBOARD\n";
+const FAKE_LOG: &str = "#!/bin/sh\ncat >> CLIPBOARD\necho >> CLIPBOARD\n";
+const FAKE_XSEL: &str = "#!/bin/sh
+file=CLIPBOARD
+if [ \"$2\" = \"-p\" ]; then file=CLIPBOARD.primary; fi
+case \"$1\" in
+  -i) cat > $file ;;
+  -o) cat $file ;;
+  -c) : > $file ;;
+esac
+";
+
+fn install_fake_tool(dir: &Path, name: &str, script: &str, clipboard: &Path) {
+    let path = dir.join(name);
+    let script = script
+        .replacen("\n", "\nPATH=/usr/bin:/bin\n", 1)
+        .replace("CLIPBOARD", clipboard.to_str().unwrap());
+    fs::write(&path, script).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn read_clipboard(clipboard: &Path) -> String {
+    fs::read_to_string(clipboard).unwrap_or_default()
+}
+
+fn wait_for_clipboard(clipboard: &Path, expected: &str) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if read_clipboard(clipboard) == expected {
+            return true;
+        }
+        sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+fn get(args: &[&str], rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+fn check_clipboard_is_cleared(rooster_file: &std::path::PathBuf, clipboard: &Path) {
+    // Cleared after the timeout
+    get(
+        &["rooster", "get", "youtube", "--clear-after", "1"],
+        rooster_file,
+    );
+    assert_eq!(read_clipboard(clipboard), "abcd");
+    assert!(wait_for_clipboard(clipboard, ""));
+
+    // Something else was copied in the meantime, so it's left alone
+    get(
+        &["rooster", "get", "youtube", "--clear-after", "1"],
+        rooster_file,
+    );
+    assert_eq!(read_clipboard(clipboard), "abcd");
+    fs::write(clipboard, "something else").unwrap();
+    sleep(Duration::from_secs(3));
+    assert_eq!(read_clipboard(clipboard), "something else");
+
+    // Never cleared
+    get(&["rooster", "get", "youtube", "--no-clear"], rooster_file);
+    sleep(Duration::from_secs(3));
+    assert_eq!(read_clipboard(clipboard), "abcd");
+}
+
+fn check_selections(rooster_file: &std::path::PathBuf, clipboard: &Path) {
+    let primary = clipboard.with_extension("primary");
+    fs::write(clipboard, "").unwrap();
+
+    let output = get(
+        &[
+            "rooster",
+            "get",
+            "youtube",
+            "--selection",
+            "primary",
+            "--no-clear",
+        ],
+        rooster_file,
+    );
+    assert!(output.contains("copied to primary selection, paste with a middle click"));
+    assert_eq!(read_clipboard(&primary), "abcd");
+    assert_eq!(read_clipboard(clipboard), "");
+
+    fs::write(&primary, "").unwrap();
+    let output = get(
+        &[
+            "rooster",
+            "get",
+            "youtube",
+            "--selection",
+            "both",
+            "--clear-after",
+            "1",
+        ],
+        rooster_file,
+    );
+    assert!(output.contains("copied to clipboard and primary selection"));
+    assert_eq!(read_clipboard(&primary), "abcd");
+    assert_eq!(read_clipboard(clipboard), "abcd");
+    assert!(wait_for_clipboard(&primary, ""));
+    assert!(wait_for_clipboard(clipboard, ""));
+}
+
+// Everything happens in a single test, because the tests of a file share the environment
+#[test]
+fn test_clipboard() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let original_path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::remove_var("ROOSTER_CLIPBOARD_CMD");
+    std::env::remove_var("SSH_TTY");
+
+    // Wayland
+    let wayland_dir = tempfile::tempdir().unwrap();
+    let clipboard = wayland_dir.path().join("clipboard");
+    install_fake_tool(wayland_dir.path(), "wl-copy", FAKE_WL_COPY, &clipboard);
+    install_fake_tool(wayland_dir.path(), "wl-paste", FAKE_WL_PASTE, &clipboard);
+    std::env::set_var("PATH", wayland_dir.path());
+    std::env::set_var("XDG_SESSION_TYPE", "wayland");
+    check_clipboard_is_cleared(&rooster_file, &clipboard);
+    check_selections(&rooster_file, &clipboard);
+
+    // The helper that clears the clipboard doesn't keep the password file locked
+    get(
+        &[
+            "rooster",
+            "generate",
+            "Twitch",
+            "tw@example.com",
+            "--clear-after",
+            "5",
+        ],
+        &rooster_file,
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "delete", "Twitch"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert!(wait_for_clipboard(&clipboard, ""));
+
+    // X11
+    let x11_dir = tempfile::tempdir().unwrap();
+    let clipboard = x11_dir.path().join("clipboard");
+    install_fake_tool(x11_dir.path(), "xsel", FAKE_XSEL, &clipboard);
+    std::env::set_var("PATH", x11_dir.path());
+    std::env::set_var("XDG_SESSION_TYPE", "x11");
+    check_clipboard_is_cleared(&rooster_file, &clipboard);
+    check_selections(&rooster_file, &clipboard);
+
+    // Through the terminal with OSC 52, when asked to or over SSH without a display
+    fs::write(&clipboard, "").unwrap();
+    get(&["rooster", "get", "youtube", "--osc52"], &rooster_file);
+    assert_eq!(read_clipboard(&clipboard), "");
+    std::env::set_var("SSH_TTY", "/dev/pts/0");
+    std::env::remove_var("DISPLAY");
+    std::env::remove_var("WAYLAND_DISPLAY");
+    get(&["rooster", "get", "youtube"], &rooster_file);
+    assert_eq!(read_clipboard(&clipboard), "");
+    std::env::set_var("DISPLAY", ":0");
+    get(&["rooster", "get", "youtube", "--no-clear"], &rooster_file);
+    assert_eq!(read_clipboard(&clipboard), "abcd");
+    std::env::remove_var("SSH_TTY");
+
+    // pbcopy, which has no option to clear the clipboard
+    let pbcopy_dir = tempfile::tempdir().unwrap();
+    let clipboard = pbcopy_dir.path().join("clipboard");
+    install_fake_tool(pbcopy_dir.path(), "pbcopy", FAKE_PBCOPY, &clipboard);
+    install_fake_tool(pbcopy_dir.path(), "pbpaste", FAKE_PBPASTE, &clipboard);
+    std::env::set_var("PATH", pbcopy_dir.path());
+    check_clipboard_is_cleared(&rooster_file, &clipboard);
+
+    // A tool of the user's choice, with its arguments, quoted like in a shell
+    let custom_dir = tempfile::tempdir().unwrap();
+    let clipboard = custom_dir.path().join("clipboard");
+    install_fake_tool(custom_dir.path(), "my-copy", FAKE_CUSTOM, &clipboard);
+    std::env::set_var(
+        "ROOSTER_CLIPBOARD_CMD",
+        format!(
+            "{} \"--from stdin\"",
+            custom_dir.path().join("my-copy").display()
+        ),
+    );
+    let output = get(&["rooster", "get", "youtube"], &rooster_file);
+    assert!(!output.contains("something went wrong"));
+    assert_eq!(read_clipboard(&clipboard), "--from stdin abcd");
+
+    // The username, then the password and the one-time code, as ENTER is hit
+    install_fake_tool(custom_dir.path(), "my-log", FAKE_LOG, &clipboard);
+    std::env::set_var("ROOSTER_CLIPBOARD_CMD", custom_dir.path().join("my-log"));
+    fs::write(&clipboard, "").unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "youtube", "--sequence"],
+            &mut CursorInputOutput::new("", "xxxx\n\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(read_clipboard(&clipboard), "yt@example.com\nabcd\n");
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "youtube", "--otp", "JBSWY3DPEHPK3PXP"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    fs::write(&clipboard, "").unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "youtube", "--sequence"],
+            &mut CursorInputOutput::new("", "xxxx\n\n\n"),
+            &rooster_file
+        )
+    );
+    let copied = read_clipboard(&clipboard);
+    let copied: Vec<&str> = copied.lines().collect();
+    assert_eq!(copied.len(), 3);
+    assert_eq!(copied[0..2], ["yt@example.com", "abcd"]);
+    assert!(copied[2].len() == 6 && copied[2].chars().all(|c| c.is_ascii_digit()));
+
+    // The tool fails
+    std::env::set_var("ROOSTER_CLIPBOARD_CMD", "false");
+    let output = get(&["rooster", "get", "youtube"], &rooster_file);
+    assert!(output.contains("something went wrong"));
+
+    std::env::remove_var("ROOSTER_CLIPBOARD_CMD");
+    std::env::set_var("PATH", original_path);
+}
@@ -42,3 +42,46 @@ fn test_command_delete() {
     assert!(!output_as_string.contains("Youtube"));
     assert!(!output_as_string.contains("yt@example.com"));
 }
+
+#[test]
+fn test_command_delete_asks_which_password() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for app in ["Google Home", "GitHub"] {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "generate", "-s", app, "me@example.com"],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    // `get git` would pick GitHub right away, but deleting needs a confirmation
+    let mut io = CursorInputOutput::new("", "xxxx\n1\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "delete", "git"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("type \"1\""));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("GitHub"));
+    assert!(output_as_string.contains("Google Home"));
+}
@@ -85,7 +85,77 @@ fn test_command_export_csv() {
     );
     let output_as_vecu8 = io.stdout_cursor.into_inner();
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
-    assert_eq!(output_as_string, "Youtube,yt@example.com,abcd\n");
+    let fields: Vec<&str> = output_as_string.trim_end().split(',').collect();
+    assert_eq!(
+        &fields[..7],
+        ["Youtube", "yt@example.com", "abcd", "", "", "", ""]
+    );
+    // Created and updated at, in seconds since 1970
+    assert_eq!(fields.len(), 9);
+    assert!(fields[7].parse::<i64>().unwrap() > 1_600_000_000);
+    assert_eq!(fields[7], fields[8]);
+}
+
+#[test]
+fn test_command_export_csv_keeps_dates() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    let import_file = tempfile();
+    std::fs::write(
+        &import_file,
+        "Youtube,yt@example.com,abcd,,,,,1500000000,1600000000\n",
+    )
+    .unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "csv", import_file.to_str().unwrap()],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "csv"], &mut io, &rooster_file)
+    );
+    let export_file = tempfile();
+    std::fs::write(&export_file, io.stdout_cursor.into_inner()).unwrap();
+
+    let other_rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nyyyy\n"),
+            &other_rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "csv", export_file.to_str().unwrap()],
+            &mut CursorInputOutput::new("", "yyyy\n"),
+            &other_rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "yyyy\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "json"], &mut io, &other_rooster_file)
+    );
+    let document: Value = serde_json::from_slice(&io.stdout_cursor.into_inner()).unwrap();
+    assert_eq!(document["passwords"][0]["created_at"], 1500000000);
+    assert_eq!(document["passwords"][0]["updated_at"], 1600000000);
 }
 
 #[test]
@@ -116,5 +186,214 @@ fn test_command_export_1password() {
     );
     let output_as_vecu8 = io.stdout_cursor.into_inner();
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
-    assert_eq!(output_as_string, "Youtube,yt@example.com,abcd\n");
+    assert_eq!(
+        output_as_string,
+        "Title,Website,Username,Password,Notes,Type\nYoutube,,yt@example.com,abcd,,Login\n"
+    );
+}
+
+/// The name, username, password, URL and notes of each password, as exported to JSON
+fn export_json(master_password: &str, rooster_file: &std::path::PathBuf) -> Vec<Value> {
+    let mut io = CursorInputOutput::new("", &format!("{}\n", master_password));
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "json"], &mut io, rooster_file)
+    );
+    let document: Value = serde_json::from_slice(&io.stdout_cursor.into_inner()).unwrap();
+    document["passwords"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|password| {
+            serde_json::json!([
+                password["name"],
+                password["username"],
+                password["password"],
+                password["url"],
+                password["notes"],
+            ])
+        })
+        .collect()
+}
+
+#[test]
+fn test_command_export_1password_round_trip() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Bank, \"main\" account",
+                "me@example.com",
+                "--url",
+                "https://bank.example.com",
+                "--notes",
+                "PIN is 1234,\nnot 4321",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n\"ef,gh\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "1password"], &mut io, &rooster_file)
+    );
+    let export_file = tempfile();
+    std::fs::write(&export_file, io.stdout_cursor.into_inner()).unwrap();
+
+    let other_rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nyyyy\n"),
+            &other_rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "1password",
+                export_file.to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", "yyyy\n"),
+            &other_rooster_file
+        )
+    );
+
+    let passwords = export_json("xxxx", &rooster_file);
+    assert_eq!(passwords.len(), 2);
+    assert_eq!(passwords, export_json("yyyy", &other_rooster_file));
+}
+
+#[test]
+fn test_command_export_rooster_bundle() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for (app, username, tag) in [
+        ("Youtube", "yt@example.com", "video"),
+        ("Netflix", "nf@example.com", "video"),
+        ("Gmail", "me@gmail.com", "mail"),
+    ] {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", app, username, "--tag", tag],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    // The passwords must match
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "rooster-bundle",
+                "--recipient-password",
+                "--tag",
+                "video"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nshared\nshraed\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\nshared\nshared\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "rooster-bundle",
+                "--recipient-password",
+                "--tag",
+                "video"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let bundle = String::from_utf8(io.stdout_cursor.into_inner()).unwrap();
+    assert!(bundle.starts_with("-----BEGIN ROOSTER BUNDLE-----\nPasswords: 2\n"));
+    assert!(!bundle.contains("abcd"));
+    let included = String::from_utf8(io.stderr_cursor.into_inner()).unwrap();
+    assert!(included.contains("Netflix, Youtube"));
+
+    let bundle_file = tempfile();
+    std::fs::write(&bundle_file, bundle).unwrap();
+    let bundle_path = bundle_file.to_str().unwrap();
+
+    let other_rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nyyyy\n"),
+            &other_rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "import", "rooster-bundle", bundle_path],
+            &mut CursorInputOutput::new("", "yyyy\nwrong\n"),
+            &other_rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "rooster-bundle", bundle_path],
+            &mut CursorInputOutput::new("", "yyyy\nshared\n"),
+            &other_rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "yyyy\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "json"], &mut io, &other_rooster_file)
+    );
+    let document: Value = serde_json::from_slice(&io.stdout_cursor.into_inner()).unwrap();
+    let passwords = document["passwords"].as_array().unwrap();
+    assert_eq!(passwords.len(), 2);
+    assert_eq!(passwords[0]["name"], "Netflix");
+    assert_eq!(passwords[1]["password"], "abcd");
+    assert_eq!(passwords[1]["tags"][0], "video");
 }
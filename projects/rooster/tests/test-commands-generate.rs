@@ -42,3 +42,75 @@ fn test_command_generate() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("yt@example.com"));
 }
+
+#[test]
+fn test_command_generate_passphrase() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--words",
+                "5",
+                "--separator",
+                "+",
+                "--capitalize",
+                "--digit"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("about 70 bits of entropy"));
+
+    let passphrase = output_as_string
+        .split("Here is your password: ")
+        .nth(1)
+        .unwrap()
+        .split('\u{1b}')
+        .next()
+        .unwrap();
+    let words = passphrase.split('+').collect::<Vec<&str>>();
+    assert_eq!(words.len(), 5);
+    assert!(words
+        .iter()
+        .all(|word| word.starts_with(char::is_uppercase)));
+    assert_eq!(passphrase.chars().filter(|c| c.is_ascii_digit()).count(), 1);
+
+    // An invalid number of words generates nothing
+    for words in ["0", "2"] {
+        assert_eq!(
+            1,
+            main_with_args(
+                &[
+                    "rooster",
+                    "generate",
+                    "-s",
+                    "Netflix",
+                    "nf@example.com",
+                    "--words",
+                    words
+                ],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+    }
+}
@@ -59,3 +59,48 @@ fn test_command_get() {
     assert!(output_as_string.contains("efgh"));
     assert!(output_as_string.contains("second@example.com"));
 }
+
+#[test]
+fn test_command_get_ranks_matches() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for (app, password) in [("Google Home", "abcd"), ("GitHub", "efgh")] {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", app, "me@example.com"],
+                &mut CursorInputOutput::new("", &format!("xxxx\n{}\n", password)),
+                &rooster_file
+            )
+        );
+    }
+
+    // Both match well, so the best one is offered first
+    let mut io = CursorInputOutput::new("", "xxxx\n1\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "gh"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Type a number from 1 to 2"));
+    assert!(output_as_string.contains("efgh"));
+
+    // A clear winner is picked without asking
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "git"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(!output_as_string.contains("Type a number"));
+    assert!(output_as_string.contains("efgh"));
+}
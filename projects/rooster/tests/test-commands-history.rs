@@ -0,0 +1,119 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_history_and_rollback() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nfirst\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "history", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("There are no previous passwords for Youtube yet"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nsecond\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nthird\n"),
+            &rooster_file
+        )
+    );
+
+    // Previous passwords are hidden unless asked for
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "history", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("1 ******** (replaced just now)"));
+    assert!(output_as_string.contains("2 ******** (replaced just now)"));
+    assert!(!output_as_string.contains("second"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "history", "-s", "youtube", "2"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("first"));
+    assert!(!output_as_string.contains("second"));
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "history", "-s", "youtube", "3"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Rolling back to the most recent previous password
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "rollback", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Password: second"));
+
+    // The password we rolled back from is in the history now
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "history", "-s", "youtube"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("1 third"));
+    assert!(output_as_string.contains("2 first"));
+    assert!(!output_as_string.contains("second"));
+}
@@ -195,4 +195,572 @@ fn test_command_import_1password() {
     assert!(output_as_string.contains("abcd"));
     assert!(output_as_string.contains("yt@example.com"));
     assert!(output_as_string.contains("Youtube"));
+    assert!(output_as_string.contains("URL: youtube.com"));
+    assert!(output_as_string.contains("Notes: Note"));
+}
+
+fn init() -> std::path::PathBuf {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    rooster_file
+}
+
+fn write_file(content: &str) -> std::path::PathBuf {
+    let path = tempfile();
+    File::create(path.clone())
+        .unwrap()
+        .write_all(content.as_bytes())
+        .unwrap();
+    path
+}
+
+fn import(args: &[&str], rooster_file: &std::path::PathBuf) -> i32 {
+    let mut args = args.to_vec();
+    args.splice(0..0, ["rooster", "import"]);
+    main_with_args(
+        &args,
+        &mut CursorInputOutput::new("", "xxxx\n"),
+        rooster_file,
+    )
+}
+
+/// Everything Rooster knows about a password, as printed by `get --output json`
+fn get(name: &str, rooster_file: &std::path::PathBuf) -> serde_json::Value {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", name, "--output", "json"],
+            &mut io,
+            rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    serde_json::from_slice(output_as_vecu8.as_slice()).unwrap()
+}
+
+/// When a password was created and last updated, as printed by `list --output json`
+fn dates(name: &str, rooster_file: &std::path::PathBuf) -> (u64, u64) {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list", "--output", "json"],
+            &mut io,
+            rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let document: serde_json::Value = serde_json::from_slice(output_as_vecu8.as_slice()).unwrap();
+    let password = document["passwords"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|password| password["name"] == name)
+        .unwrap();
+    (
+        password["created_at"].as_u64().unwrap(),
+        password["updated_at"].as_u64().unwrap(),
+    )
+}
+
+#[test]
+fn test_command_import_csv_map() {
+    let rooster_file = init();
+    let file = write_file(
+        "Title,Website,Login,Secret,Comment\n\
+         Youtube,https://youtube.com,yt@example.com,abcd,My notes\n\
+         ,https://www.github.com/login,gh@example.com,efgh,\n\
+         Nothing,,me@example.com,,\n",
+    );
+    let path = file.to_str().unwrap();
+
+    // Columns that don't exist are reported before anything is imported
+    assert_eq!(
+        1,
+        import(
+            &["csv", path, "--map", "name=Title,password=Password"],
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        import(
+            &[
+                "csv",
+                path,
+                "--map",
+                "name=Title,url=Website,username=Login,password=Secret,notes=Comment"
+            ],
+            &rooster_file
+        )
+    );
+    let youtube = get("Youtube", &rooster_file);
+    assert_eq!(youtube["username"], "yt@example.com");
+    assert_eq!(youtube["password"], "abcd");
+    assert_eq!(youtube["url"], "https://youtube.com");
+    assert_eq!(youtube["notes"], "My notes");
+    // Named after the website when there's no name
+    assert_eq!(get("github.com", &rooster_file)["password"], "efgh");
+}
+
+#[test]
+fn test_command_import_browsers_and_lastpass() {
+    let rooster_file = init();
+
+    let chrome = write_file(
+        "name,url,username,password\n\
+         youtube.com,https://youtube.com/,yt@example.com,abcd\n",
+    );
+    assert_eq!(
+        0,
+        import(&["chrome", chrome.to_str().unwrap()], &rooster_file)
+    );
+    assert_eq!(get("youtube.com", &rooster_file)["password"], "abcd");
+
+    let firefox = write_file(
+        "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n\
+         \"https://github.com\",\"gh@example.com\",\"efgh\",,\"https://github.com\",\"{0}\",\"1505554169000\",\"1605554169000\",\"1605554169000\"\n",
+    );
+    assert_eq!(
+        0,
+        import(&["firefox", firefox.to_str().unwrap()], &rooster_file)
+    );
+    let github = get("github.com", &rooster_file);
+    assert_eq!(github["username"], "gh@example.com");
+    assert_eq!(github["password"], "efgh");
+    assert_eq!(dates("github.com", &rooster_file), (1505554169, 1605554169));
+
+    let lastpass = write_file(
+        "url,username,password,totp,extra,name,grouping,fav\n\
+         https://mail.example.com,me@example.com,ijkl,JBSWY3DPEHPK3PXP,Some notes,Webmail,Work,0\n\
+         http://sn,,,,Secure note,Note,,0\n",
+    );
+    assert_eq!(
+        0,
+        import(&["lastpass", lastpass.to_str().unwrap()], &rooster_file)
+    );
+    let webmail = get("Webmail", &rooster_file);
+    assert_eq!(webmail["password"], "ijkl");
+    assert_eq!(webmail["notes"], "Some notes");
+    assert_eq!(webmail["tags"][0], "Work");
+}
+
+#[test]
+fn test_command_import_bitwarden() {
+    let rooster_file = init();
+    let file = write_file(
+        r#"{
+          "encrypted": false,
+          "folders": [{"id": "f1", "name": "Work"}],
+          "items": [
+            {
+              "id": "i1", "folderId": "f1", "type": 1, "name": "GitHub", "notes": "Team account",
+              "creationDate": "2017-09-16T09:42:49.000Z", "revisionDate": "2020-11-16T19:16:09.000Z",
+              "fields": [{"name": "PIN", "value": "1234", "type": 1}],
+              "login": {
+                "uris": [{"match": null, "uri": "https://github.com"}],
+                "username": "gh@example.com", "password": "abcd", "totp": "JBSWY3DPEHPK3PXP"
+              }
+            },
+            {"id": "i2", "folderId": null, "type": 3, "name": "Visa", "card": {}}
+          ]
+        }"#,
+    );
+
+    assert_eq!(
+        0,
+        import(&["bitwarden", file.to_str().unwrap()], &rooster_file)
+    );
+    let github = get("GitHub", &rooster_file);
+    assert_eq!(github["username"], "gh@example.com");
+    assert_eq!(github["password"], "abcd");
+    assert_eq!(github["url"], "https://github.com");
+    assert_eq!(github["notes"], "Team account");
+    assert_eq!(github["tags"][0], "Work");
+    assert_eq!(github["fields"][0]["name"], "PIN");
+    assert_eq!(github["fields"][0]["secret"], true);
+    assert_eq!(dates("GitHub", &rooster_file), (1505554969, 1605554169));
+
+    let encrypted = write_file(r#"{"encrypted": true, "passwordProtected": true}"#);
+    assert_eq!(
+        1,
+        import(&["bitwarden", encrypted.to_str().unwrap()], &rooster_file)
+    );
+}
+
+#[test]
+fn test_command_import_keepass() {
+    let rooster_file = init();
+    let file = write_file(
+        r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<KeePassFile>
+  <Meta><RecycleBinUUID>YmluYmluYmluYmluYmluYg==</RecycleBinUUID></Meta>
+  <Root>
+    <Group>
+      <UUID>cm9vdHJvb3Ryb290cm9vdA==</UUID>
+      <Name>Database</Name>
+      <Group>
+        <UUID>d29ya3dvcmt3b3Jrd29yaw==</UUID>
+        <Name>Work</Name>
+        <Entry>
+          <String><Key>Title</Key><Value>GitHub</Value></String>
+          <String><Key>UserName</Key><Value>gh@example.com</Value></String>
+          <String><Key>Password</Key><Value ProtectInMemory="True">a&lt;b&amp;c</Value></String>
+          <String><Key>URL</Key><Value>https://github.com</Value></String>
+          <String><Key>Notes</Key><Value>Team account</Value></String>
+          <String><Key>Recovery</Key><Value ProtectInMemory="True">1234</Value></String>
+          <Times>
+            <CreationTime>2017-09-16T09:42:49Z</CreationTime>
+            <LastModificationTime>2020-11-16T19:16:09Z</LastModificationTime>
+          </Times>
+          <History>
+            <Entry>
+              <String><Key>Title</Key><Value>GitHub</Value></String>
+              <String><Key>Password</Key><Value>old</Value></String>
+            </Entry>
+          </History>
+        </Entry>
+      </Group>
+      <Group>
+        <UUID>YmluYmluYmluYmluYmluYg==</UUID>
+        <Name>Recycle Bin</Name>
+        <Entry>
+          <String><Key>Title</Key><Value>Deleted</Value></String>
+          <String><Key>Password</Key><Value>efgh</Value></String>
+        </Entry>
+      </Group>
+    </Group>
+  </Root>
+</KeePassFile>
+"#,
+    );
+
+    assert_eq!(
+        0,
+        import(&["keepass", file.to_str().unwrap()], &rooster_file)
+    );
+    let github = get("GitHub", &rooster_file);
+    assert_eq!(github["username"], "gh@example.com");
+    assert_eq!(github["password"], "a<b&c");
+    assert_eq!(github["url"], "https://github.com");
+    assert_eq!(github["notes"], "Team account");
+    assert_eq!(github["tags"][0], "Work");
+    assert_eq!(github["fields"][0]["name"], "Recovery");
+    assert_eq!(github["fields"][0]["secret"], true);
+    assert_eq!(dates("GitHub", &rooster_file), (1505554969, 1605554169));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "get", "-s", "Deleted"], &mut io, &rooster_file)
+    );
+}
+
+#[test]
+fn test_command_import_pass() {
+    let rooster_file = init();
+    let store = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(store.path().join("email")).unwrap();
+    std::fs::create_dir_all(store.path().join(".git")).unwrap();
+    std::fs::write(
+        store.path().join("email/gmail.com.gpg"),
+        "abcd\nlogin: me@gmail.com\nurl: https://mail.google.com\nRecovery: 1234\n",
+    )
+    .unwrap();
+    std::fs::write(store.path().join(".git/config.gpg"), "efgh\n").unwrap();
+    std::fs::write(store.path().join(".gpg-id"), "ABCDEF\n").unwrap();
+
+    // The files are not really encrypted here
+    assert_eq!(
+        0,
+        import(
+            &[
+                "pass",
+                store.path().to_str().unwrap(),
+                "--decrypt-command",
+                "cat"
+            ],
+            &rooster_file
+        )
+    );
+    let gmail = get("email/gmail.com", &rooster_file);
+    assert_eq!(gmail["username"], "me@gmail.com");
+    assert_eq!(gmail["password"], "abcd");
+    assert_eq!(gmail["url"], "https://mail.google.com");
+    assert_eq!(gmail["notes"], "Recovery: 1234");
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "get", "-s", "config"], &mut io, &rooster_file)
+    );
+}
+
+#[test]
+fn test_command_import_on_conflict() {
+    let rooster_file = init();
+    let first = write_file(
+        "{\"passwords\":[{\"name\":\"Youtube\",\"username\":\"yt@example.com\",\"password\":\"abcd\",\"created_at\":1605554169,\"updated_at\":1605554169}]}",
+    );
+    let older = write_file(
+        "{\"passwords\":[{\"name\":\"Youtube\",\"username\":\"old@example.com\",\"password\":\"efgh\",\"created_at\":1505554169,\"updated_at\":1505554169}]}",
+    );
+    let newer = write_file(
+        "{\"passwords\":[{\"name\":\"youtube\",\"username\":\"new@example.com\",\"password\":\"ijkl\",\"created_at\":1705554169,\"updated_at\":1705554169}]}",
+    );
+    assert_eq!(0, import(&["json", first.to_str().unwrap()], &rooster_file));
+
+    // Skipped by default
+    assert_eq!(0, import(&["json", older.to_str().unwrap()], &rooster_file));
+    assert_eq!(get("Youtube", &rooster_file)["password"], "abcd");
+
+    // Only newer passwords replace the ones in the password store
+    for (file, password) in [(&older, "abcd"), (&newer, "ijkl")] {
+        assert_eq!(
+            0,
+            import(
+                &["json", file.to_str().unwrap(), "--on-conflict", "newer"],
+                &rooster_file
+            )
+        );
+        assert_eq!(get("Youtube", &rooster_file)["password"], password);
+    }
+
+    // Overwritten passwords are kept in the history
+    assert_eq!(
+        0,
+        import(
+            &[
+                "json",
+                older.to_str().unwrap(),
+                "--on-conflict",
+                "overwrite"
+            ],
+            &rooster_file
+        )
+    );
+    let youtube = get("Youtube", &rooster_file);
+    assert_eq!(youtube["username"], "old@example.com");
+    assert_eq!(youtube["password"], "efgh");
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "history", "Youtube", "--show"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("ijkl"));
+    assert!(output_as_string.contains("abcd"));
+
+    for _ in 0..2 {
+        assert_eq!(
+            0,
+            import(
+                &["json", first.to_str().unwrap(), "--on-conflict", "rename"],
+                &rooster_file
+            )
+        );
+    }
+    assert_eq!(get("Youtube (2)", &rooster_file)["password"], "abcd");
+    assert_eq!(get("Youtube (3)", &rooster_file)["password"], "abcd");
+
+    // Asked about, showing what changed but not the passwords
+    let mut io = CursorInputOutput::new("", "xxxx\nwhat?\no\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "json",
+                newer.to_str().unwrap(),
+                "--on-conflict",
+                "ask"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Username: old@example.com -> new@example.com"));
+    assert!(output_as_string.contains("Password: ******** -> ******** (changed)"));
+    assert!(!output_as_string.contains("ijkl"));
+    assert!(output_as_string.contains("I did not get that."));
+    assert_eq!(get("Youtube", &rooster_file)["password"], "ijkl");
+}
+
+#[test]
+fn test_command_import_dry_run() {
+    let rooster_file = init();
+    let file = write_file(
+        "{\"passwords\":[{\"name\":\"Youtube\",\"username\":\"yt@example.com\",\"password\":\"abcd\",\"created_at\":1605554169,\"updated_at\":1605554169}]}",
+    );
+    assert_eq!(0, import(&["json", file.to_str().unwrap()], &rooster_file));
+    let before = std::fs::read(&rooster_file).unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                "--dry-run",
+                "--on-conflict",
+                "rename",
+                write_file("Youtube,yt@example.com,efgh\nGoogle,me@example.com,ijkl\n")
+                    .to_str()
+                    .unwrap()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube, would be imported as Youtube (2)"));
+    assert!(output_as_string.contains("Google, would be imported"));
+
+    // Nothing was written, not even a backup
+    assert_eq!(before, std::fs::read(&rooster_file).unwrap());
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "get", "-s", "Google"], &mut io, &rooster_file)
+    );
+}
+
+#[test]
+fn test_command_import_reports_bad_lines() {
+    let rooster_file = init();
+    let mut content = b"Youtube,yt@example.com,abcd\n\
+        Google,me@example.com,efgh,,,,not json\n\
+        Broken\n\
+        Nopassword,me@example.com,\n"
+        .to_vec();
+    content.extend_from_slice(b"Invalid,\xff\xfe,ijkl\nGithub,gh@example.com,mnop\n");
+    let file = tempfile();
+    File::create(file.clone())
+        .unwrap()
+        .write_all(&content)
+        .unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "csv", file.to_str().unwrap()],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Errors: 4"));
+    assert!(output_as_string.contains("Line 2, Google: invalid custom fields"));
+    assert!(output_as_string
+        .contains("Line 3, Broken: expected at least a name, a username and a password"));
+    assert!(output_as_string.contains("Line 4, Nopassword: no password"));
+    assert!(output_as_string.contains("Line 5: "));
+
+    // The lines around the bad ones are imported
+    assert_eq!(get("Youtube", &rooster_file)["password"], "abcd");
+    assert_eq!(get("Github", &rooster_file)["password"], "mnop");
+
+    let mapped = write_file(
+        "Title,Password,Created,Updated\n\
+         Dropbox,abcd,2017-09-16,2020-11-16 19:16:09\n\
+         Slack,efgh,last week,\n",
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "import",
+                "csv",
+                mapped.to_str().unwrap(),
+                "--map",
+                "name=Title,password=Password,created_at=Created,updated_at=Updated",
+                "--output",
+                "json"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let document: serde_json::Value = serde_json::from_slice(output_as_vecu8.as_slice()).unwrap();
+    assert_eq!(document["imported"], 1);
+    assert_eq!(document["errors"], 1);
+    assert_eq!(document["problems"][0]["line"], 3);
+    assert_eq!(document["problems"][0]["name"], "Slack");
+    assert_eq!(
+        document["problems"][0]["reason"],
+        "\"last week\" is not a date"
+    );
+    assert_eq!(dates("Dropbox", &rooster_file), (1505520000, 1605554169));
+}
+
+#[test]
+fn test_command_import_keeps_dates() {
+    let rooster_file = init();
+
+    let csv = write_file(
+        "Youtube,yt@example.com,abcd,,,,,1500000000,1600000000\n\
+         Google,me@example.com,efgh,,,,,yesterday,\n",
+    );
+    assert_eq!(0, import(&["csv", csv.to_str().unwrap()], &rooster_file));
+    assert_eq!(dates("Youtube", &rooster_file), (1500000000, 1600000000));
+    // A date that can't be read is reported, not replaced with the current date
+    assert!(!get_all_names(&rooster_file).contains(&"Google".to_owned()));
+
+    let one_password = write_file(
+        "Title,Website,Username,Password,Notes,Type,Created Date,Modified Date\n\
+         GitHub,github.com,me,ijkl,,Login,2017-07-14T02:40:00Z,2020-09-13T12:26:40Z\n",
+    );
+    assert_eq!(
+        0,
+        import(
+            &["1password", one_password.to_str().unwrap()],
+            &rooster_file
+        )
+    );
+    assert_eq!(dates("GitHub", &rooster_file), (1500000000, 1600000000));
+}
+
+fn get_all_names(rooster_file: &std::path::PathBuf) -> Vec<String> {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list", "--output", "json"],
+            &mut io,
+            rooster_file
+        )
+    );
+    let document: serde_json::Value =
+        serde_json::from_slice(&io.stdout_cursor.into_inner()).unwrap();
+    document["passwords"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|password| password["name"].as_str().unwrap().to_owned())
+        .collect()
 }
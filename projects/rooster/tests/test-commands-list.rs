@@ -52,3 +52,72 @@ fn test_command_list() {
     assert!(output_as_string.contains("Google"));
     assert!(output_as_string.contains("google@example.com"));
 }
+
+fn list(args: &[&str], rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(0, main_with_args(args, &mut io, rooster_file));
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned()
+}
+
+#[test]
+fn test_command_list_filters() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    for (app, username, tag) in [
+        ("Youtube", "yt@example.com", "video"),
+        ("Google", "me@gmail.com", "work"),
+        ("Gitlab", "me@example.com", "work"),
+    ] {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", app, username, "--tag", tag],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    let output = list(&["rooster", "list", "g"], &rooster_file);
+    assert!(output.contains("Gitlab") && output.contains("Google"));
+    assert!(!output.contains("Youtube"));
+
+    let output = list(&["rooster", "list", "--username", "EXAMPLE"], &rooster_file);
+    assert!(output.contains("Gitlab") && output.contains("Youtube"));
+    assert!(!output.contains("Google"));
+
+    let output = list(
+        &["rooster", "list", "--tag", "work", "--username", "gmail"],
+        &rooster_file,
+    );
+    assert!(output.contains("Google"));
+    assert!(!output.contains("Gitlab") && !output.contains("Youtube"));
+
+    let output = list(&["rooster", "list", "--older-than", "90d"], &rooster_file);
+    assert!(output.contains("No passwords match"));
+
+    let output = list(
+        &[
+            "rooster",
+            "list",
+            "--sort",
+            "created",
+            "--column",
+            "created,updated",
+        ],
+        &rooster_file,
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 3);
+    let date = |s: &str| s.len() == 10 && s.chars().filter(|c| *c == '-').count() == 2;
+    let words: Vec<&str> = lines[0].split_whitespace().collect();
+    assert!(words.iter().filter(|w| date(w)).count() == 2);
+}
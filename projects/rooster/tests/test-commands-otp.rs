@@ -0,0 +1,85 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_otp() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--otp",
+                "otpauth://totp/Youtube:yt%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Google", "google@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "otp", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Here is your one-time code for Youtube"));
+    assert!(output_as_string.lines().any(|line| {
+        let code = line
+            .trim_start_matches("\u{1b}[32m")
+            .trim_end_matches("\u{1b}[0m");
+        code.len() == 8 && code.chars().all(|c| c.is_ascii_digit())
+    }));
+    assert!(output_as_string.contains("It is valid for"));
+    assert!(!output_as_string.contains("abcd"));
+
+    // No secret
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "otp", "-s", "google"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("there is no two factor authentication secret for Google"));
+
+    // Removing the secret
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "youtube", "--otp", ""],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "otp", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
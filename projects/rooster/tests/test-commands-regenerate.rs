@@ -67,4 +67,116 @@ fn test_command_regenerate() {
     let output_2_as_string = String::from_utf8_lossy(output_2_as_vecu8.as_slice());
 
     assert_ne!(output_1_as_string, output_2_as_string);
+
+    // Passphrase
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "regenerate", "-s", "Youtube", "--words", "4"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("about 52 bits of entropy"));
+}
+
+fn get_password(rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let start = output_as_string.find("Password: ").unwrap() + "Password: ".len();
+    output_as_string[start..]
+        .chars()
+        .take_while(|c| *c != '\u{1b}' && *c != '\n')
+        .collect()
+}
+
+#[test]
+fn test_command_regenerate_keeps_policy() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--policy",
+                "length=12 lower=no upper=no symbol=no",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let password = get_password(&rooster_file);
+    assert_eq!(password.len(), 12);
+    assert!(password.chars().all(|c| c.is_ascii_digit()));
+
+    // The policy is used again without asking for it
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "regenerate", "-s", "Youtube"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let password = get_password(&rooster_file);
+    assert_eq!(password.len(), 12);
+    assert!(password.chars().all(|c| c.is_ascii_digit()));
+
+    // A new policy replaces it
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "regenerate",
+                "-s",
+                "Youtube",
+                "--policy",
+                "length=8 digit=no symbol=no",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let password = get_password(&rooster_file);
+    assert_eq!(password.len(), 8);
+    assert!(password.chars().all(|c| c.is_ascii_alphabetic()));
+
+    // Policies that can't be satisfied are refused
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "regenerate",
+                "-s",
+                "Youtube",
+                "--policy",
+                "length=4 digit=5",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
 }
@@ -0,0 +1,67 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Write;
+
+#[test]
+fn test_command_restore_backup() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "restore-backup"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("There are no backups"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // The file gets corrupted, for instance because of a full disk
+    File::create(rooster_file.clone())
+        .unwrap()
+        .write_all(&[0, 0, 0, 3, 1])
+        .unwrap();
+
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+
+    // Backup 1 is the file right before Youtube was added
+    let mut io = CursorInputOutput::new("", "2\n1\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "restore-backup"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("I need a number between 1 and 1"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("No passwords on record yet"));
+}
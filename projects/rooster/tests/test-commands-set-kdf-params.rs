@@ -0,0 +1,121 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Read;
+
+#[test]
+fn test_command_set_kdf_params_argon2id() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "set-kdf-params", "argon2id", "4096", "0", "1"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("must be > 0"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "set-kdf-params", "argon2id", "4", "1", "1"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("not valid for argon2id"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "set-kdf-params", "argon2id", "4096", "1", "16"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Run with --force to force"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "set-kdf-params",
+                "argon2id",
+                "--force",
+                "4096",
+                "1",
+                "16"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut rooster_file_contents = Vec::new();
+    File::open(rooster_file.as_path())
+        .unwrap()
+        .read_to_end(&mut rooster_file_contents)
+        .unwrap();
+    assert_eq!(
+        &rooster_file_contents[4..17],
+        &[2, 0, 0, 16, 0, 0, 0, 0, 1, 0, 0, 0, 16]
+    );
+
+    // The password file still opens with the same master password
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+
+    // And switching back to scrypt works too
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-scrypt-params", "12", "8", "1"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut rooster_file_contents = Vec::new();
+    File::open(rooster_file.as_path())
+        .unwrap()
+        .read_to_end(&mut rooster_file_contents)
+        .unwrap();
+    assert_eq!(
+        &rooster_file_contents[4..14],
+        &[1, 12, 0, 0, 0, 8, 0, 0, 0, 1]
+    );
+}
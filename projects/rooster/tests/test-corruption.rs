@@ -2,7 +2,7 @@ mod helpers;
 
 use crate::helpers::prelude::*;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 
 #[test]
 fn test_corruption() {
@@ -33,6 +33,42 @@ fn test_corruption() {
         ])
         .unwrap();
 
+    // The file is a v2 file, so it gets checked when upgrading
+    let mut io = CursorInputOutput::new("", "xxxx\ny\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Your Rooster file is corrupted"));
+}
+
+#[test]
+fn test_corruption_v3() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Flips a bit in the last byte of the encrypted blob
+    let mut rooster_file_contents = Vec::new();
+    File::open(rooster_file.as_path())
+        .unwrap()
+        .read_to_end(&mut rooster_file_contents)
+        .unwrap();
+    let last = rooster_file_contents.len() - 1;
+    rooster_file_contents[last] ^= 1;
+    File::create(rooster_file.clone())
+        .unwrap()
+        .write_all(&rooster_file_contents)
+        .unwrap();
+
     let mut io = CursorInputOutput::new("", "xxxx\n");
     assert_eq!(
         1,
@@ -0,0 +1,90 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+fn hold_lock(rooster_file: &Path, operation: libc::c_int) -> File {
+    let mut lock_path = rooster_file.as_os_str().to_os_string();
+    lock_path.push(".lock");
+    let file = File::open(lock_path).unwrap();
+    assert_eq!(0, unsafe { libc::flock(file.as_raw_fd(), operation) });
+    file
+}
+
+#[test]
+fn test_no_lock_file_without_password_file() {
+    let rooster_file = tempfile();
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("First time user"));
+
+    let mut lock_path = rooster_file.as_os_str().to_os_string();
+    lock_path.push(".lock");
+    assert!(!Path::new(&lock_path).exists());
+}
+
+#[test]
+fn test_locking() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Another Rooster is changing the password file
+    let lock = hold_lock(&rooster_file, libc::LOCK_EX);
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "list"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("another Rooster is using your password file"));
+    drop(lock);
+
+    // Another Rooster is reading the password file
+    let lock = hold_lock(&rooster_file, libc::LOCK_SH);
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "add", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Waiting for the other Rooster to be done
+    let release = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        drop(lock);
+    });
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "--wait", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    release.join().unwrap();
+}
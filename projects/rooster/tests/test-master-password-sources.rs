@@ -0,0 +1,108 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs;
+use std::os::unix::io::AsRawFd;
+
+fn list(args: &[&str], rooster_file: &std::path::PathBuf) -> (i32, String) {
+    // Nothing is typed on the TTY, so asking for the password would fail
+    let mut io = CursorInputOutput::new("", "");
+    let code = main_with_args(args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+// Everything happens in a single test, because the tests of a file share the environment
+#[test]
+fn test_master_password_sources() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    std::env::remove_var("ROOSTER_MASTER_PASSWORD_COMMAND");
+
+    let dir = tempfile::tempdir().unwrap();
+    let right = dir.path().join("right");
+    let wrong = dir.path().join("wrong");
+    fs::write(&right, "xxxx\nsome other line\n").unwrap();
+    fs::write(&wrong, "nok\n").unwrap();
+
+    // From a file
+    let (code, _) = list(
+        &[
+            "rooster",
+            "list",
+            "--password-file",
+            right.to_str().unwrap(),
+        ],
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+
+    // A wrong password is not asked again
+    let (code, output) = list(
+        &[
+            "rooster",
+            "list",
+            "--password-file",
+            wrong.to_str().unwrap(),
+        ],
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert!(output.contains("that's not the right password"));
+    assert!(!output.contains("Let's try again"));
+
+    let (code, output) = list(
+        &["rooster", "list", "--password-file", "/does/not/exist"],
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert!(output.contains("I could not read your master password"));
+
+    // From a file descriptor
+    // Rooster doesn't close it, it belongs to whoever opened it
+    let password = fs::File::open(&right).unwrap();
+    let fd = password.as_raw_fd().to_string();
+    let (code, _) = list(&["rooster", "list", "--password-fd", &fd], &rooster_file);
+    assert_eq!(0, code);
+
+    let (code, output) = list(&["rooster", "list", "--password-fd", "1"], &rooster_file);
+    assert_eq!(1, code);
+    assert!(output.contains("the file descriptor must be 3 or more"));
+
+    // From a command
+    std::env::set_var(
+        "ROOSTER_MASTER_PASSWORD_COMMAND",
+        format!("cat {}", right.display()),
+    );
+    let (code, _) = list(&["rooster", "list"], &rooster_file);
+    assert_eq!(0, code);
+
+    std::env::set_var("ROOSTER_MASTER_PASSWORD_COMMAND", "false");
+    let (code, output) = list(&["rooster", "list"], &rooster_file);
+    assert_eq!(1, code);
+    assert!(output.contains("I could not read your master password"));
+
+    // The options win over the command
+    let (code, _) = list(
+        &[
+            "rooster",
+            "list",
+            "--password-file",
+            right.to_str().unwrap(),
+        ],
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+
+    std::env::remove_var("ROOSTER_MASTER_PASSWORD_COMMAND");
+}
@@ -0,0 +1,221 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write;
+
+/// Runs a command with `--output json`, and parses what it printed on the standard output
+fn run_json(args: &[&str], input: &str, rooster_file: &std::path::PathBuf) -> (i32, Value) {
+    let mut args = args.to_vec();
+    args.extend_from_slice(&["--output", "json"]);
+    let mut io = CursorInputOutput::new("", input);
+    let code = main_with_args(&args, &mut io, rooster_file);
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned();
+    (code, serde_json::from_str(&output_as_string).unwrap())
+}
+
+fn init() -> std::path::PathBuf {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--tag",
+                "video"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    rooster_file
+}
+
+#[test]
+fn test_output_json_list_and_get() {
+    let rooster_file = init();
+
+    let (code, document) = run_json(&["rooster", "list"], "xxxx\n", &rooster_file);
+    assert_eq!(0, code);
+    assert_eq!(document["passwords"][0]["name"], "Youtube");
+    assert_eq!(document["passwords"][0]["username"], "yt@example.com");
+    assert_eq!(document["passwords"][0]["tags"][0], "video");
+    assert!(document["passwords"][0]["password"].is_null());
+
+    let (code, document) = run_json(
+        &["rooster", "get", "-s", "youtube"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(document["name"], "Youtube");
+    assert_eq!(document["username"], "yt@example.com");
+    assert_eq!(document["password"], "abcd");
+
+    let (code, document) = run_json(
+        &["rooster", "get", "-s", "youtube", "--field", "username"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(document["field"], "username");
+    assert_eq!(document["value"], "yt@example.com");
+}
+
+#[test]
+fn test_output_json_generate() {
+    let rooster_file = init();
+
+    let (code, document) = run_json(
+        &["rooster", "generate", "-s", "Google", "me@example.com"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(document["name"], "Google");
+    assert_eq!(document["username"], "me@example.com");
+    assert_eq!(document["password"].as_str().unwrap().len(), 32);
+    assert!(document["entropy_bits"].as_f64().unwrap() > 100.0);
+}
+
+#[test]
+fn test_output_json_import() {
+    let rooster_file = init();
+
+    let import_file_csv = tempfile();
+    File::create(import_file_csv.clone())
+        .unwrap()
+        .write_all("Youtube,yt@example.com,abcd\nGoogle,me@example.com,efgh\n".as_bytes())
+        .unwrap();
+
+    let (code, document) = run_json(
+        &[
+            "rooster",
+            "import",
+            "csv",
+            import_file_csv.as_path().to_str().unwrap(),
+        ],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    assert_eq!(document["imported"], 1);
+    assert_eq!(document["warnings"], 1);
+    assert_eq!(document["errors"], 0);
+    assert_eq!(document["problems"][0]["name"], "Youtube");
+    assert_eq!(document["problems"][0]["code"], "app_exists");
+}
+
+#[test]
+fn test_output_json_errors() {
+    let rooster_file = init();
+
+    let (code, document) = run_json(
+        &["rooster", "get", "-s", "nothing"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert_eq!(document["error"]["code"], "no_such_app");
+    assert!(document["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("can't find any passwords"));
+
+    let (code, document) = run_json(
+        &["rooster", "add", "Youtube", "yt@example.com"],
+        "xxxx\n",
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert_eq!(document["error"]["code"], "app_exists");
+
+    let wrong = tempfile();
+    File::create(wrong.clone())
+        .unwrap()
+        .write_all(b"nok\n")
+        .unwrap();
+    let (code, document) = run_json(
+        &[
+            "rooster",
+            "list",
+            "--password-file",
+            wrong.to_str().unwrap(),
+        ],
+        "",
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    assert_eq!(document["error"]["code"], "decryption");
+}
+
+#[test]
+fn test_output_json_prompts_go_to_stderr() {
+    let rooster_file = init();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube Kids", "kids@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+
+    // Several passwords match, so Rooster asks which one, without breaking the JSON document
+    let mut io = CursorInputOutput::new("", "xxxx\n2\n");
+    let code = main_with_args(
+        &["rooster", "get", "-s", "youtub", "--output", "json"],
+        &mut io,
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    let stderr = String::from_utf8(io.stderr_cursor.into_inner()).unwrap();
+    assert!(stderr.contains("Type a number from 1 to 2"));
+    let stdout = String::from_utf8(io.stdout_cursor.into_inner()).unwrap();
+    let document: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(document["name"], "Youtube Kids");
+}
+
+#[test]
+fn test_output_json_shows_prompts_right_away() {
+    let rooster_file = init();
+
+    // A wrong password is asked again, and the retry message is not kept for the JSON document
+    let mut io = CursorInputOutput::new("", "nope\nxxxx\n");
+    let code = main_with_args(
+        &["rooster", "get", "-s", "youtube", "--output", "json"],
+        &mut io,
+        &rooster_file,
+    );
+    assert_eq!(0, code);
+    let stderr = String::from_utf8(io.stderr_cursor.into_inner()).unwrap();
+    assert!(stderr.contains("Let's try again"));
+    let stdout = String::from_utf8(io.stdout_cursor.into_inner()).unwrap();
+    let document: Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(document["password"], "abcd");
+
+    // Errors show up as they happen too, not only in the JSON document
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    let code = main_with_args(
+        &["rooster", "get", "-s", "nothing", "--output", "json"],
+        &mut io,
+        &rooster_file,
+    );
+    assert_eq!(1, code);
+    let stderr = String::from_utf8(io.stderr_cursor.into_inner()).unwrap();
+    assert!(stderr.contains("can't find any passwords"));
+}
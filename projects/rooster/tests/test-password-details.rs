@@ -0,0 +1,212 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::Write;
+
+fn get(args: &[&str], rooster_file: &std::path::Path) -> (i32, String) {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    let code = main_with_args(args, &mut io, &rooster_file.to_path_buf());
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    (
+        code,
+        String::from_utf8_lossy(output_as_vecu8.as_slice()).into_owned(),
+    )
+}
+
+#[test]
+fn test_password_details() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--url",
+                "https://youtube.com",
+                "--notes",
+                "Recovery codes are in the drawer",
+                "--tag",
+                "video",
+                "--tag",
+                "google",
+                "--field",
+                "Recovery email=me@example.com",
+                "--secret-field",
+                "PIN=1234",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let (code, output) = get(&["rooster", "get", "-s", "youtube"], &rooster_file);
+    assert_eq!(code, 0);
+    assert!(output.contains("URL: https://youtube.com"));
+    assert!(output.contains("Notes: Recovery codes are in the drawer"));
+    assert!(output.contains("Tags: video, google"));
+    assert!(output.contains("Recovery email: me@example.com"));
+    assert!(output.contains("PIN: ********"));
+    assert!(!output.contains("1234"));
+
+    let (code, output) = get(
+        &["rooster", "get", "-s", "youtube", "--field", "pin"],
+        &rooster_file,
+    );
+    assert_eq!(code, 0);
+    assert!(output.contains("1234"));
+    assert!(!output.contains("abcd"));
+
+    let (code, output) = get(
+        &["rooster", "get", "-s", "youtube", "--field", "url"],
+        &rooster_file,
+    );
+    assert_eq!(code, 0);
+    assert!(output.contains("https://youtube.com"));
+
+    let (code, _) = get(
+        &["rooster", "get", "-s", "youtube", "--field", "nothing"],
+        &rooster_file,
+    );
+    assert_eq!(code, 1);
+
+    // Changing only the details doesn't ask for a new password
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "change",
+                "-s",
+                "youtube",
+                "--url",
+                "",
+                "--tag",
+                "music",
+                "--remove-field",
+                "PIN",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let (code, output) = get(&["rooster", "get", "-s", "youtube"], &rooster_file);
+    assert_eq!(code, 0);
+    assert!(output.contains("abcd"));
+    assert!(!output.contains("URL:"));
+    assert!(output.contains("Tags: music"));
+    assert!(!output.contains("PIN"));
+    assert!(output.contains("Recovery email: me@example.com"));
+
+    // Without details, the password is changed and the details are kept
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+    let (code, output) = get(&["rooster", "get", "-s", "youtube"], &rooster_file);
+    assert_eq!(code, 0);
+    assert!(output.contains("efgh"));
+    assert!(output.contains("Tags: music"));
+}
+
+#[test]
+fn test_password_details_export_import() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "add",
+                "-s",
+                "Youtube",
+                "yt@example.com",
+                "--url",
+                "https://youtube.com",
+                "--notes",
+                "Some notes, with a comma",
+                "--tag",
+                "video",
+                "--tag",
+                "google",
+                "--secret-field",
+                "PIN=1234",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    for format in &["csv", "json"] {
+        let (code, export) = get(&["rooster", "export", format], &rooster_file);
+        assert_eq!(code, 0);
+
+        let import_file = tempfile();
+        File::create(&import_file)
+            .unwrap()
+            .write_all(export.as_bytes())
+            .unwrap();
+
+        let other_rooster_file = tempfile();
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "init", "--force-for-tests"],
+                &mut CursorInputOutput::new("", "\nxxxx\n"),
+                &other_rooster_file
+            )
+        );
+        assert_eq!(
+            0,
+            main_with_args(
+                &[
+                    "rooster",
+                    "import",
+                    format,
+                    import_file.as_path().to_str().unwrap()
+                ],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &other_rooster_file
+            )
+        );
+
+        let (code, output) = get(&["rooster", "get", "-s", "youtube"], &other_rooster_file);
+        assert_eq!(code, 0);
+        assert!(output.contains("URL: https://youtube.com"));
+        assert!(output.contains("Notes: Some notes, with a comma"));
+        assert!(output.contains("Tags: video, google"));
+        assert!(output.contains("PIN: ********"));
+
+        let (code, output) = get(
+            &["rooster", "get", "-s", "youtube", "--field", "PIN"],
+            &other_rooster_file,
+        );
+        assert_eq!(code, 0);
+        assert!(output.contains("1234"));
+    }
+}
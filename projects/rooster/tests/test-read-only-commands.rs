@@ -0,0 +1,66 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_read_only_commands_do_not_rewrite_the_file() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let before = std::fs::read(&rooster_file).unwrap();
+    let modified_before = std::fs::metadata(&rooster_file)
+        .unwrap()
+        .modified()
+        .unwrap();
+
+    for args in [
+        &["rooster", "get", "-s", "youtube"][..],
+        &["rooster", "list"][..],
+        &["rooster", "export", "json"][..],
+    ] {
+        assert_eq!(
+            0,
+            main_with_args(
+                args,
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+    }
+
+    // Same nonce, same file, and no backup either
+    assert_eq!(std::fs::read(&rooster_file).unwrap(), before);
+    assert_eq!(
+        std::fs::metadata(&rooster_file)
+            .unwrap()
+            .modified()
+            .unwrap(),
+        modified_before
+    );
+
+    // A command that fails doesn't rewrite the file either
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "delete", "nothing"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(std::fs::read(&rooster_file).unwrap(), before);
+}
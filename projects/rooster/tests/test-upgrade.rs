@@ -0,0 +1,143 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+fn write_v2_file(rooster_file: &Path) {
+    // Creates a v2 file, with master password "xxxx" and a single password for Youtube
+    File::create(rooster_file)
+        .unwrap()
+        .write_all(&[
+            0o000, 0o000, 0o000, 0o002, 0o014, 0o000, 0o000, 0o000, 0o010, 0o000, 0o000, 0o000,
+            0o001, 0o055, 0o321, 0o304, 0o151, 0o134, 0o102, 0o017, 0o053, 0o275, 0o054, 0o231,
+            0o244, 0o002, 0o170, 0o320, 0o207, 0o140, 0o007, 0o307, 0o360, 0o147, 0o311, 0o166,
+            0o375, 0o231, 0o200, 0o024, 0o176, 0o072, 0o213, 0o041, 0o342, 0o152, 0o355, 0o056,
+            0o212, 0o026, 0o301, 0o334, 0o334, 0o127, 0o101, 0o306, 0o244, 0o270, 0o371, 0o263,
+            0o175, 0o145, 0o244, 0o313, 0o355, 0o126, 0o251, 0o053, 0o365, 0o076, 0o133, 0o200,
+            0o034, 0o067, 0o244, 0o053, 0o354, 0o365, 0o357, 0o272, 0o340, 0o272, 0o021, 0o237,
+            0o340, 0o032, 0o347, 0o205, 0o141, 0o020, 0o333, 0o024, 0o070, 0o143, 0o103, 0o136,
+            0o353, 0o117, 0o057, 0o326, 0o252, 0o020, 0o025, 0o060, 0o100, 0o312, 0o107, 0o014,
+            0o240, 0o250, 0o130, 0o205, 0o361, 0o001, 0o125, 0o260, 0o222, 0o225, 0o347, 0o312,
+            0o042, 0o207, 0o054, 0o316, 0o235, 0o112, 0o356, 0o336, 0o303, 0o333, 0o026, 0o013,
+            0o070, 0o011, 0o121, 0o006, 0o272, 0o105, 0o052, 0o047, 0o252, 0o052, 0o336, 0o023,
+            0o326, 0o001, 0o227, 0o340, 0o202, 0o007, 0o252, 0o204, 0o163, 0o277, 0o366, 0o023,
+            0o127, 0o231, 0o257, 0o020, 0o022, 0o025, 0o030, 0o051, 0o233, 0o175, 0o171, 0o305,
+            0o136, 0o076, 0o106, 0o022, 0o156, 0o147, 0o263, 0o332, 0o243, 0o231, 0o136, 0o367,
+            0o116, 0o377, 0o246, 0o002, 0o316, 0o263, 0o173, 0o277, 0o041, 0o216, 0o366, 0o262,
+            0o126, 0o300, 0o147, 0o166, 0o031, 0o024, 0o275, 0o310, 0o110, 0o056, 0o120, 0o374,
+            0o353, 0o243, 0o172, 0o342, 0o246, 0o067, 0o071, 0o267, 0o064, 0o013, 0o013, 0o172,
+            0o275, 0o065, 0o207, 0o204, 0o075, 0o162, 0o363, 0o257, 0o354, 0o301, 0o202, 0o264,
+            0o305, 0o114, 0o105, 0o325, 0o344, 0o142, 0o205, 0o354, 0o012, 0o214, 0o200, 0o154,
+            0o303, 0o110, 0o251, 0o215, 0o143, 0o244, 0o337, 0o105, 0o114, 0o326, 0o156, 0o040,
+            0o106, 0o370, 0o325, 0o010, 0o137, 0o272, 0o007, 0o035, 0o045, 0o276, 0o056, 0o367,
+            0o072, 0o376, 0o314, 0o344, 0o126,
+        ])
+        .unwrap();
+}
+
+#[test]
+fn test_upgrade_from_v2() {
+    let rooster_file = tempfile();
+
+    write_v2_file(&rooster_file);
+
+    // Refusing the upgrade leaves the file as is
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\nn\n"),
+            &rooster_file
+        )
+    );
+    let mut rooster_file_contents = Vec::new();
+    File::open(rooster_file.as_path())
+        .unwrap()
+        .read_to_end(&mut rooster_file_contents)
+        .unwrap();
+    assert_eq!(&rooster_file_contents[0..4], &[0, 0, 0, 2]);
+
+    // Accepting the upgrade rewrites the file as v3
+    let mut io = CursorInputOutput::new("", "xxxx\ny\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert!(output_as_string.contains("yt@example.com"));
+
+    let mut rooster_file_contents = Vec::new();
+    File::open(rooster_file.as_path())
+        .unwrap()
+        .read_to_end(&mut rooster_file_contents)
+        .unwrap();
+    assert_eq!(&rooster_file_contents[0..4], &[0, 0, 0, 3]);
+    assert_eq!(
+        &rooster_file_contents[4..14],
+        &[1, 12, 0, 0, 0, 8, 0, 0, 0, 1]
+    );
+
+    // The v3 file opens without asking to upgrade
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+}
+
+#[test]
+fn test_upgrade_waits_for_other_readers() {
+    let rooster_file = tempfile();
+    write_v2_file(&rooster_file);
+
+    // Another Rooster is reading the password file, it can't be upgraded under its feet
+    let mut lock_path = rooster_file.as_os_str().to_os_string();
+    lock_path.push(".lock");
+    let lock = File::create(lock_path).unwrap();
+    assert_eq!(0, unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_SH) });
+
+    let mut io = CursorInputOutput::new("", "xxxx\ny\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("another Rooster is using your password file"));
+    assert_eq!(&std::fs::read(&rooster_file).unwrap()[0..4], &[0, 0, 0, 2]);
+}
+
+#[test]
+fn test_upgrade_with_password_fd() {
+    let rooster_file = tempfile();
+    write_v2_file(&rooster_file);
+
+    // The file descriptor can only be read once, the upgrade must reuse the password
+    let dir = tempfile::tempdir().unwrap();
+    let password_file = dir.path().join("password");
+    std::fs::write(&password_file, "xxxx\n").unwrap();
+    let password = File::open(&password_file).unwrap();
+    let fd = password.as_raw_fd().to_string();
+
+    let mut io = CursorInputOutput::new("", "y\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube", "--password-fd", &fd],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert_eq!(&std::fs::read(&rooster_file).unwrap()[0..4], &[0, 0, 0, 3]);
+}